@@ -1,38 +1,130 @@
-﻿use crate::gui::Gui;
-use easy_imgui::{Ui, UiBuilder, FontAtlasMut};
+﻿use crate::{
+	cli::LaunchArgs,
+	config::{Configuration, Session, WindowState},
+	gui::Gui
+};
+use easy_imgui::{easy_imgui_sys::ImGuiStyle, FontAtlasMut, Ui, UiBuilder};
 use easy_imgui_window::{
 	winit,
 	winit::event_loop::EventLoop,
 	Application, AppHandler, Args, EventResult
 };
-use winit::event::WindowEvent;
+use winit::{
+	dpi::{PhysicalPosition, PhysicalSize},
+	event::WindowEvent
+};
+
+pub const TITLE: &str = "Pose2D Editor";
 
 pub struct App {
-	gui: Gui
+	gui: Gui,
+	title: String,
+	// Style as created by ImGui, themes and scaling are applied on top of it.
+	base_style: ImGuiStyle
 }
 
 impl App {
-	pub fn run() {
+	pub fn run(args: LaunchArgs) {
 		let event_loop = EventLoop::new().unwrap();
-		let mut main = AppHandler::<App>::default();
-		main.attributes().title = String::from("Pose2D Editor");
+		
+		let mut cfg = Configuration::open();
+		Self::apply_args(&mut cfg, args);
+		let window = cfg.window;
+		
+		let mut main = AppHandler::<App>::new(cfg);
+		let attributes = main.attributes();
+		attributes.title = String::from(TITLE);
+		if let Some(window) = window {
+			let [width, height] = window.size;
+			attributes.inner_size = Some(PhysicalSize::new(width, height).into());
+			attributes.position = window.position.map(|[x, y]| PhysicalPosition::new(x, y).into());
+			attributes.maximized = window.maximized;
+		}
+		
 		event_loop.run_app(&mut main).expect("Event loop failure");
 	}
+	
+	// Command line arguments take the place of the restored session.
+	fn apply_args(cfg: &mut Configuration, args: LaunchArgs) {
+		if let Some(images) = args.images {
+			cfg.image_path_override = Some(images.to_string_lossy().into_owned());
+		}
+		
+		if let Some(schema) = args.schema {
+			cfg.session = Session {
+				schema: Some(schema),
+				..Session::default()
+			};
+		}
+		
+		if let Some(view) = args.view {
+			cfg.session.view = Some(view);
+			cfg.session.bone = None;
+			cfg.session.tab = None;
+		}
+		
+		cfg.merge_sources = args.merge;
+	}
 }
 
 impl Application for App {
 	type UserEvent = ();
-	type Data = ();
+	type Data = Configuration;
 
-	fn new(_args: Args<'_, Self::Data>) -> Self {
+	fn new(args: Args<'_, Self::Data>) -> Self {
+		let renderer = args.window.renderer();
+		let gl = renderer.gl_context().clone();
+		let cfg = std::mem::take(args.data);
+		let gui = Gui::new(gl, cfg);
+		
+		// SAFETY: the renderer owns the only ImGui context.
+		let mut imgui = unsafe { renderer.imgui().set_current() };
+		let base_style = *imgui.style().get();
+		gui.apply_style(imgui.style(), &base_style);
+		
 		Self {
-			gui: Gui::new()
+			gui,
+			title: String::from(TITLE),
+			base_style
 		}
 	}
 
 	fn window_event(&mut self, args: Args<'_, Self::Data>, _event: WindowEvent, res: EventResult) {
+		let restyled = self.gui.take_style_changed();
+		if restyled {
+			// SAFETY: the renderer owns the only ImGui context.
+			let mut imgui = unsafe { args.window.renderer().imgui().set_current() };
+			self.gui.apply_style(imgui.style(), &self.base_style);
+		}
+		
+		let window = args.window.main_window().window();
+		
+		let title = self.gui.title();
+		if title != self.title {
+			window.set_title(&title);
+			self.title = title;
+		}
+		
+		if restyled {
+			window.request_redraw();
+		}
+		
+		// Closing is vetoed while there are unsaved changes, the prompt
+		// shown instead decides whether the app actually quits.
 		if res.window_closed {
-			self.gui.close();
+			self.gui.request_quit();
+			window.request_redraw();
+		}
+		
+		if self.gui.should_quit() {
+			let size = window.inner_size();
+			let state = WindowState {
+				size: [ size.width, size.height ],
+				position: window.outer_position().ok().map(|pos| [ pos.x, pos.y ]),
+				maximized: window.is_maximized()
+			};
+			
+			self.gui.close(state);
 			args.event_loop.exit();
 		}
 	}
@@ -40,7 +132,7 @@ impl Application for App {
 
 impl UiBuilder for App {
 	fn build_custom_atlas(&mut self, atlas: &mut FontAtlasMut<'_, Self>) {
-		self.gui.images.borrow_mut().build_atlas(atlas);
+		self.gui.build_fonts(atlas);
 	}
 	
 	fn do_ui(&mut self, ui: &Ui<Self>) {
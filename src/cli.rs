@@ -0,0 +1,452 @@
+﻿use crate::{
+	config::Configuration,
+	render::{self, RenderOptions},
+	schema::{
+		data::SchemaData,
+		diff::{self, SchemaDiff},
+		merge::SchemaMerge,
+		project::SchemaProject,
+		skeleton,
+		SchemaSerializer
+	}
+};
+use std::{
+	env,
+	fs,
+	path::{self, Path, PathBuf},
+	process
+};
+
+const USAGE: &str = "\
+Usage: pose2d [SCHEMA] [OPTIONS]
+       pose2d <COMMAND> [ARGS]
+
+Arguments:
+  [SCHEMA]          Schema file to open
+
+Options:
+  --images <DIR>    Image directory to use for this session
+  --view <NAME>     View to select after opening
+  -h, --help        Print this help
+
+Commands:
+  diff              Compare two schema files
+  merge             Merge two versions of a schema with their common base
+  render            Draw a view with its bones to a PNG file";
+
+const DIFF_USAGE: &str = "\
+Usage: pose2d diff <OLD> <NEW> [OPTIONS]
+
+Lists views and bones added, removed or renamed, bones moved and image changes.
+Exits with 0 when the schemas match, 1 when they differ and 2 on errors.
+
+Options:
+  --threshold <N>   Smallest move to report, in normalized units [default: 0.001]
+  --format <FMT>    Output format, text or json [default: text]
+  -h, --help        Print this help";
+
+const MERGE_USAGE: &str = "\
+Usage: pose2d merge <BASE> <OURS> <THEIRS> [OPTIONS]
+
+Merges two edited versions of a schema with their common base, matching views and bones by name.
+Fields changed on both sides become conflicts, which take our side unless resolved in the editor.
+Exits with 0 on a clean merge, 1 when conflicts were found and 2 on errors.
+
+Options:
+  -o, --output <FILE>  File to write the result to [default: OURS]
+  --gui                Open the editor to resolve conflicts
+  -h, --help           Print this help
+
+As a git merge driver, for the paths that hold schemas only:
+  git config merge.pose2d.driver \"pose2d merge %O %A %B\"
+  echo \"schemas/*.xml merge=pose2d\" >> .gitattributes
+Files that aren't schemas are rejected with exit code 2, so git leaves them as a normal conflict.
+
+As a git merge tool:
+  git config mergetool.pose2d.cmd 'pose2d merge --gui \"$BASE\" \"$LOCAL\" \"$REMOTE\" -o \"$MERGED\"'";
+
+const RENDER_USAGE: &str = "\
+Usage: pose2d render <SCHEMA> [OPTIONS]
+
+Draws a view's image with its bones to a PNG file, using the editor's image directory and marker style.
+Exits with 0 on success and 2 on errors.
+
+Options:
+  --view <NAME>        View to render, needed when the schema has more than one
+  --image <FILE>       Image of the view to draw [default: the first]
+  --out <FILE>         PNG file to write [default: <VIEW>.png]
+  --images <DIR>       Image directory to use instead of the configured one
+  --labels             Draw bone labels
+  --hierarchy <FILE>   Draw lines from parent to child bones, read from a skeleton bone list
+  --hit-map            Shade the area each marker can be clicked in
+  -h, --help           Print this help";
+
+// Command
+
+pub enum Command {
+	Launch(LaunchArgs),
+	Diff(DiffArgs),
+	Merge(MergeArgs),
+	Render(RenderArgs)
+}
+
+impl Command {
+	// Parses the process arguments, exiting with usage on invalid input.
+	pub fn from_env() -> Self {
+		let mut args = env::args().skip(1).peekable();
+		match args.peek().map(String::as_str) {
+			Some("diff") => {
+				args.next();
+				Self::Diff(exit_on_error(DiffArgs::parse(args), DIFF_USAGE))
+			},
+			Some("merge") => {
+				args.next();
+				Self::Merge(exit_on_error(MergeArgs::parse(args), MERGE_USAGE))
+			},
+			Some("render") => {
+				args.next();
+				Self::Render(exit_on_error(RenderArgs::parse(args), RENDER_USAGE))
+			},
+			_ => Self::Launch(exit_on_error(LaunchArgs::parse(args), USAGE))
+		}
+	}
+}
+
+// Prints usage for help requests and errors, then exits.
+fn exit_on_error<T>(result: Result<Option<T>, String>, usage: &str) -> T {
+	match result {
+		Ok(Some(args)) => args,
+		Ok(None) => {
+			println!("{}", usage);
+			process::exit(0);
+		},
+		Err(err) => {
+			eprintln!("error: {}\n\n{}", err, usage);
+			process::exit(2);
+		}
+	}
+}
+
+// LaunchArgs
+
+#[derive(Default)]
+pub struct LaunchArgs {
+	pub schema: Option<PathBuf>,
+	pub images: Option<PathBuf>,
+	pub view: Option<String>,
+	// Base, ours and theirs of a merge to resolve in the editor.
+	pub merge: Option<[PathBuf; 3]>
+}
+
+impl LaunchArgs {
+	// Returns `None` when help was requested.
+	pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Option<Self>, String> {
+		let mut result = Self::default();
+		let mut args = args.into_iter();
+		
+		while let Some(arg) = args.next() {
+			let flag = Flag::split(&arg);
+			match flag.name.as_str() {
+				"-h" | "--help" => return Ok(None),
+				"--images" => result.images = Some(absolute(flag.value(&mut args)?.into())),
+				"--view" => result.view = Some(flag.value(&mut args)?),
+				_ if flag.name.starts_with('-') && flag.name.len() > 1 => {
+					return Err(format!("unknown option '{}'", flag.name))
+				},
+				_ if result.schema.is_none() => result.schema = Some(absolute(arg.into())),
+				_ => return Err(format!("unexpected argument '{}'", arg))
+			}
+		}
+		
+		Ok(Some(result))
+	}
+}
+
+fn absolute(path: PathBuf) -> PathBuf {
+	path::absolute(&path).unwrap_or(path)
+}
+
+// An argument as a flag, with the value given inline as in `--flag=value`.
+struct Flag {
+	name: String,
+	inline: Option<String>
+}
+
+impl Flag {
+	// Accept both `--flag value` and `--flag=value`.
+	fn split(arg: &str) -> Self {
+		match arg.split_once('=') {
+			Some((name, value)) if name.starts_with("--") => Self { name: name.to_string(), inline: Some(value.to_string()) },
+			_ => Self { name: arg.to_string(), inline: None }
+		}
+	}
+	
+	// The inline value, or else the next argument.
+	fn value(&self, args: &mut impl Iterator<Item = String>) -> Result<String, String> {
+		self.inline.clone()
+			.or_else(|| args.next())
+			.ok_or_else(|| format!("missing value for {}", self.name))
+	}
+}
+
+fn read_schema(path: &Path) -> Result<SchemaData, String> {
+	match path.is_file() {
+		true => SchemaData::try_deserialize(path.to_path_buf())
+			.map_err(|err| format!("failed to read {}: {}", path.display(), err)),
+		false => Err(format!("file does not exist: {}", path.display()))
+	}
+}
+
+// Diff
+
+pub struct DiffArgs {
+	pub old: PathBuf,
+	pub new: PathBuf,
+	pub threshold: f32,
+	pub json: bool
+}
+
+impl DiffArgs {
+	// Returns `None` when help was requested.
+	pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Option<Self>, String> {
+		let mut paths = Vec::new();
+		let mut threshold = diff::DEFAULT_THRESHOLD;
+		let mut json = false;
+		let mut args = args.into_iter();
+		
+		while let Some(arg) = args.next() {
+			let flag = Flag::split(&arg);
+			match flag.name.as_str() {
+				"-h" | "--help" => return Ok(None),
+				"--threshold" => {
+					let text = flag.value(&mut args)?;
+					// Negative thresholds would report every bone as moved, NaN none of them.
+					threshold = text.parse().ok()
+						.filter(|x: &f32| x.is_finite() && *x >= 0.0)
+						.ok_or_else(|| format!("invalid threshold '{}'", text))?;
+				},
+				"--format" => match flag.value(&mut args)?.as_str() {
+					"text" => json = false,
+					"json" => json = true,
+					other => return Err(format!("unknown format '{}'", other))
+				},
+				_ if flag.name.starts_with('-') && flag.name.len() > 1 => {
+					return Err(format!("unknown option '{}'", flag.name))
+				},
+				_ if paths.len() < 2 => paths.push(PathBuf::from(arg)),
+				_ => return Err(format!("unexpected argument '{}'", arg))
+			}
+		}
+		
+		let [ old, new ]: [PathBuf; 2] = paths.try_into()
+			.map_err(|_| "expected an old and a new schema file".to_string())?;
+		Ok(Some(Self { old, new, threshold, json }))
+	}
+	
+	// Prints the diff and returns the exit code.
+	pub fn run(self) -> i32 {
+		let (old, new) = match read_schema(&self.old).and_then(|old| Ok((old, read_schema(&self.new)?))) {
+			Ok(schemas) => schemas,
+			Err(err) => {
+				eprintln!("error: {}", err);
+				return 2;
+			}
+		};
+		
+		let diff = SchemaDiff::compute(&old, &new, self.threshold);
+		match self.json {
+			true => println!("{}", serde_json::to_string_pretty(&diff).expect("Failed to serialize diff.")),
+			false => print!("{}", diff)
+		}
+		
+		match diff.is_empty() {
+			true => 0,
+			false => 1
+		}
+	}
+}
+
+// Merge
+
+pub struct MergeArgs {
+	pub base: PathBuf,
+	pub ours: PathBuf,
+	pub theirs: PathBuf,
+	pub output: Option<PathBuf>,
+	pub gui: bool
+}
+
+impl MergeArgs {
+	// Returns `None` when help was requested.
+	pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Option<Self>, String> {
+		let mut paths = Vec::new();
+		let mut output = None;
+		let mut gui = false;
+		let mut args = args.into_iter();
+		
+		while let Some(arg) = args.next() {
+			let flag = Flag::split(&arg);
+			match flag.name.as_str() {
+				"-h" | "--help" => return Ok(None),
+				"-o" | "--output" => output = Some(PathBuf::from(flag.value(&mut args)?)),
+				"--gui" => gui = true,
+				_ if flag.name.starts_with('-') && flag.name.len() > 1 => {
+					return Err(format!("unknown option '{}'", flag.name))
+				},
+				_ if paths.len() < 3 => paths.push(PathBuf::from(arg)),
+				_ => return Err(format!("unexpected argument '{}'", arg))
+			}
+		}
+		
+		let [ base, ours, theirs ]: [PathBuf; 3] = paths.try_into()
+			.map_err(|_| "expected a base, ours and theirs schema file".to_string())?;
+		Ok(Some(Self { base, ours, theirs, output, gui }))
+	}
+	
+	// Writes the merge, taking our side on conflicts, and returns the exit code.
+	pub fn run(&self) -> i32 {
+		let schemas = read_schema(&self.base)
+			.and_then(|base| Ok((base, read_schema(&self.ours)?, read_schema(&self.theirs)?)));
+		let (base, ours, theirs) = match schemas {
+			Ok(schemas) => schemas,
+			Err(err) => {
+				eprintln!("error: {}", err);
+				return 2;
+			}
+		};
+		
+		let merge = SchemaMerge::new(base, ours, theirs);
+		let buffer = merge.result().serialize().expect("Failed to serialize file.");
+		if let Err(err) = fs::write(self.output(), buffer) {
+			eprintln!("error: failed to write {}: {}", self.output().display(), err);
+			return 2;
+		}
+		
+		for conflict in &merge.conflicts {
+			eprintln!("conflict: {}", conflict);
+		}
+		match merge.is_clean() {
+			true => 0,
+			false => 1
+		}
+	}
+	
+	pub fn output(&self) -> &Path {
+		self.output.as_deref().unwrap_or(&self.ours)
+	}
+	
+	// Opens the written result with the merge ready to resolve.
+	pub fn launch_args(&self) -> LaunchArgs {
+		LaunchArgs {
+			schema: Some(absolute(self.output().to_path_buf())),
+			merge: Some([ &self.base, &self.ours, &self.theirs ].map(|x| absolute(x.clone()))),
+			..LaunchArgs::default()
+		}
+	}
+}
+
+// Render
+
+#[derive(Default)]
+pub struct RenderArgs {
+	pub schema: PathBuf,
+	pub view: Option<String>,
+	pub image: Option<String>,
+	pub out: Option<PathBuf>,
+	pub images: Option<PathBuf>,
+	pub labels: bool,
+	pub hierarchy: Option<PathBuf>,
+	pub hit_map: bool
+}
+
+impl RenderArgs {
+	// Returns `None` when help was requested.
+	pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Option<Self>, String> {
+		let mut result = Self::default();
+		let mut schema = None;
+		let mut args = args.into_iter();
+		
+		while let Some(arg) = args.next() {
+			let flag = Flag::split(&arg);
+			match flag.name.as_str() {
+				"-h" | "--help" => return Ok(None),
+				"--view" => result.view = Some(flag.value(&mut args)?),
+				"--image" => result.image = Some(flag.value(&mut args)?),
+				"--out" => result.out = Some(PathBuf::from(flag.value(&mut args)?)),
+				"--images" => result.images = Some(absolute(flag.value(&mut args)?.into())),
+				"--labels" => result.labels = true,
+				"--hierarchy" => result.hierarchy = Some(PathBuf::from(flag.value(&mut args)?)),
+				"--hit-map" => result.hit_map = true,
+				_ if flag.name.starts_with('-') && flag.name.len() > 1 => {
+					return Err(format!("unknown option '{}'", flag.name))
+				},
+				_ if schema.is_none() => schema = Some(PathBuf::from(arg)),
+				_ => return Err(format!("unexpected argument '{}'", arg))
+			}
+		}
+		
+		result.schema = schema.ok_or_else(|| "expected a schema file".to_string())?;
+		Ok(Some(result))
+	}
+	
+	// Writes the image and returns the exit code.
+	pub fn run(self) -> i32 {
+		match self.render() {
+			Ok(out) => {
+				println!("Rendered {}", out.display());
+				0
+			},
+			Err(err) => {
+				eprintln!("error: {}", err);
+				2
+			}
+		}
+	}
+	
+	fn render(&self) -> Result<PathBuf, String> {
+		let data = read_schema(&self.schema)?;
+		let view = match &self.view {
+			Some(name) => data.views.iter()
+				.find(|view| view.name == *name)
+				.ok_or_else(|| format!("no view named '{}'", name))?,
+			None => match data.views.as_slice() {
+				[ view ] => view,
+				views => {
+					let names: Vec<&str> = views.iter().map(|view| view.name.as_str()).collect();
+					return Err(format!("pick a view with --view: {}", names.join(", ")));
+				}
+			}
+		};
+		if let Some(image) = &self.image {
+			if !view.images.iter().any(|x| x.file == *image) {
+				return Err(format!("view '{}' has no image '{}'", view.name, image));
+			}
+		}
+		
+		// Rendering leaves the configuration as it is, even when it can't be read.
+		let mut cfg = match Configuration::load() {
+			Ok(cfg) => cfg.unwrap_or_default(),
+			Err(err) => {
+				eprintln!("warning: {}, using default settings", err);
+				Configuration::default()
+			}
+		};
+		if let Some(images) = &self.images {
+			cfg.image_path_override = Some(images.to_string_lossy().into_owned());
+		}
+		if !view.images.is_empty() && cfg.image_root().is_none() {
+			return Err("no image directory configured, set one with --images".to_string());
+		}
+		
+		let project = SchemaProject::read(&self.schema);
+		let mut options = RenderOptions::new(&cfg, &project, view, self.image.as_deref());
+		options.labels = self.labels;
+		options.hit_map = self.hit_map;
+		options.hierarchy = self.hierarchy.as_deref().map(skeleton::read_bone_list).transpose()?;
+		
+		let img = render::render(view, &options)?;
+		let out = self.out.clone().unwrap_or_else(|| PathBuf::from(format!("{}.png", view.name)));
+		img.save(&out).map_err(|err| format!("failed to write {}: {}", out.display(), err))?;
+		Ok(out)
+	}
+}
\ No newline at end of file
@@ -1,14 +1,150 @@
 ﻿use std::{
-	env::current_exe,
-	fs::{read_to_string, OpenOptions},
-	io::Write,
-	path::PathBuf
+	env,
+	fs,
+	path::{Path, PathBuf}
 };
 use serde::{Serialize, Deserialize};
+use crate::keys::Keymap;
 
-#[derive(Default, Serialize, Deserialize)]
+const APP_DIR: &str = "pose2d";
+const CONFIG_FILE: &str = "config.json";
+
+// Portable installs keep the config next to the executable. A `portable` marker
+// file enables this, as does an existing `data.json` from older versions.
+const PORTABLE_MARKER: &str = "portable";
+const PORTABLE_FILE: &str = "data.json";
+
+const DEFAULT_IMAGE_BUDGET: usize = 512;
+const MAX_RECENT_FILES: usize = 10;
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
 pub struct Configuration {
-	pub image_path: Option<String>
+	pub image_path: Option<String>,
+	// Set from the command line, applies to this session only.
+	#[serde(skip)]
+	pub image_path_override: Option<String>,
+	// Base, ours and theirs of a merge to resolve, set from the command line.
+	#[serde(skip)]
+	pub merge_sources: Option<[PathBuf; 3]>,
+	pub image_budget: usize,
+	pub blank_aspect: [u32; 2],
+	pub recent_files: Vec<PathBuf>,
+	pub window: Option<WindowState>,
+	pub ui: UiPreferences,
+	pub keys: Keymap,
+	pub session: Session
+}
+
+impl Default for Configuration {
+	fn default() -> Self {
+		Self {
+			image_path: None,
+			image_path_override: None,
+			merge_sources: None,
+			image_budget: DEFAULT_IMAGE_BUDGET,
+			blank_aspect: [1, 1],
+			recent_files: Vec::new(),
+			window: None,
+			ui: UiPreferences::default(),
+			keys: Keymap::default(),
+			session: Session::default()
+		}
+	}
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub struct WindowState {
+	pub size: [u32; 2],
+	pub position: Option<[i32; 2]>,
+	#[serde(default)]
+	pub maximized: bool
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct UiPreferences {
+	pub marker_radius: f32,
+	pub marker_color: [f32; 4],
+	pub marker_selected_color: [f32; 4],
+	pub compare_opacity: f32,
+	pub scale: f32,
+	// Uses the built-in ImGui font when unset.
+	pub font: Option<PathBuf>,
+	pub font_size: f32,
+	pub theme: Theme,
+	pub custom_theme: CustomTheme,
+	// Language to show labels in, the schema's own labels when unset.
+	pub language: Option<String>
+}
+
+impl Default for UiPreferences {
+	fn default() -> Self {
+		Self {
+			marker_radius: 10.0,
+			marker_color: [ 1.0, 1.0, 1.0, 0.65 ],
+			marker_selected_color: [ 1.0, 1.0, 1.0, 1.0 ],
+			compare_opacity: 0.5,
+			scale: 1.0,
+			font: None,
+			font_size: 13.0,
+			theme: Theme::default(),
+			custom_theme: CustomTheme::default(),
+			language: None
+		}
+	}
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+	#[default]
+	Dark,
+	Light,
+	Custom
+}
+
+// Colors laid over the dark theme when `Theme::Custom` is selected.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct CustomTheme {
+	pub background: [f32; 4],
+	pub text: [f32; 4],
+	pub frame: [f32; 4],
+	pub accent: [f32; 4]
+}
+
+impl Default for CustomTheme {
+	fn default() -> Self {
+		Self {
+			background: [ 0.12, 0.12, 0.14, 1.0 ],
+			text: [ 0.92, 0.92, 0.92, 1.0 ],
+			frame: [ 0.22, 0.22, 0.26, 1.0 ],
+			accent: [ 0.85, 0.45, 0.2, 1.0 ]
+		}
+	}
+}
+
+// Session state restored on the next launch.
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct Session {
+	pub schema: Option<PathBuf>,
+	pub view: Option<String>,
+	pub bone: Option<String>,
+	pub tab: Option<String>,
+	pub canvas: Vec<CanvasSession>,
+	// Every open schema in tab order, `schema` being the active one.
+	pub documents: Vec<PathBuf>
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CanvasSession {
+	pub view: String,
+	pub tab: String,
+	pub zoom: f32,
+	pub pan: [f32; 2]
 }
 
 impl Configuration {
@@ -16,40 +152,125 @@ impl Configuration {
 		Self::read()
 	}
 	
+	// Reads the configuration without reporting or moving anything, for commands that only
+	// use it. `None` when there is no configuration yet.
+	pub fn load() -> Result<Option<Self>, String> {
+		let Some(path) = Self::get_path().filter(|path| path.exists()) else { return Ok(None) };
+		let content = fs::read_to_string(&path)
+			.map_err(|err| format!("Failed to read {}: {}", path.display(), err))?;
+		serde_json::from_str(&content)
+			.map(Some)
+			.map_err(|err| format!("Failed to parse {}: {}", path.display(), err))
+	}
+	
+	pub fn image_root(&self) -> Option<&str> {
+		self.image_path_override.as_deref().or(self.image_path.as_deref())
+	}
+	
+	pub fn image_budget_bytes(&self) -> usize {
+		self.image_budget * 1024 * 1024
+	}
+	
+	// Recent files
+	
+	pub fn add_recent_file(&mut self, path: &Path) {
+		self.recent_files.retain(|x| x != path);
+		self.recent_files.insert(0, path.to_path_buf());
+		self.recent_files.truncate(MAX_RECENT_FILES);
+	}
+	
+	// File
+	
 	pub fn save(&self) {
-		let path = Self::get_path().unwrap();
-		let mut file = OpenOptions::new()
-			.create(true)
-			.write(true)
-			.open(path)
-			.unwrap();
+		let Some(path) = Self::get_path() else {
+			println!("No configuration directory available, settings not saved.");
+			return;
+		};
 		
-		let content = serde_json::to_string(&self).unwrap();
-		file.write_all(content.as_bytes()).expect("Failed to write config.");
-		file.set_len(content.len() as _).unwrap();
+		if let Some(dir) = path.parent() {
+			if let Err(err) = fs::create_dir_all(dir) {
+				println!("Failed to create {}: {}", dir.display(), err);
+				return;
+			}
+		}
+		
+		let content = serde_json::to_string_pretty(&self).expect("Failed to serialize config.");
+		if let Err(err) = fs::write(&path, content) {
+			println!("Failed to write config: {}", err);
+		}
 	}
 	
 	fn read() -> Configuration {
-		let path = Self::get_path().unwrap();
-		if path.exists() {
-			let content = read_to_string(path).expect("Failed to read config file.");
-			serde_json::from_str(&content).expect("Failed to parse config.")
-		} else {
+		let Some(path) = Self::get_path() else {
+			return Configuration::default()
+		};
+		
+		if !path.exists() {
 			println!("No configuration found.");
-			Configuration::default()
+			return Configuration::default()
+		}
+		
+		let content = match fs::read_to_string(&path) {
+			Ok(content) => content,
+			Err(err) => {
+				println!("Failed to read config: {}", err);
+				return Configuration::default()
+			}
+		};
+		
+		match serde_json::from_str(&content) {
+			Ok(cfg) => cfg,
+			Err(err) => {
+				// Keep the broken file around instead of overwriting it on exit.
+				println!("Failed to parse config, using defaults: {}", err);
+				let backup = path.with_extension("json.bak");
+				if fs::rename(&path, &backup).is_ok() {
+					println!("Moved broken config to {}", backup.display());
+				}
+				Configuration::default()
+			}
 		}
 	}
 	
 	fn get_path() -> Option<PathBuf> {
-		let dir = current_exe();
-		match &dir {
-			Ok(path) => {
-				match path.parent() {
-					Some(path) => Some(path.join("data.json")),
-					_ => None
-				}
-			},
-			_ => None
+		match Self::portable_dir() {
+			Some(dir) => Some(dir.join(PORTABLE_FILE)),
+			None => Some(Self::config_dir()?.join(APP_DIR).join(CONFIG_FILE))
+		}
+	}
+	
+	fn portable_dir() -> Option<PathBuf> {
+		let exe = env::current_exe().ok()?;
+		let dir = exe.parent()?;
+		
+		let is_portable = env::var_os("POSE2D_PORTABLE").is_some()
+			|| dir.join(PORTABLE_MARKER).exists()
+			|| dir.join(PORTABLE_FILE).exists();
+		
+		is_portable.then(|| dir.to_path_buf())
+	}
+	
+	#[cfg(windows)]
+	fn config_dir() -> Option<PathBuf> {
+		env::var_os("APPDATA").map(PathBuf::from)
+	}
+	
+	#[cfg(target_os = "macos")]
+	fn config_dir() -> Option<PathBuf> {
+		let home = env::var_os("HOME")?;
+		Some(PathBuf::from(home).join("Library/Application Support"))
+	}
+	
+	#[cfg(not(any(windows, target_os = "macos")))]
+	fn config_dir() -> Option<PathBuf> {
+		// Relative paths in XDG_CONFIG_HOME are invalid per the spec and ignored.
+		let xdg = env::var_os("XDG_CONFIG_HOME")
+			.map(PathBuf::from)
+			.filter(|path| path.is_absolute());
+		
+		match xdg {
+			Some(path) => Some(path),
+			None => Some(PathBuf::from(env::var_os("HOME")?).join(".config"))
 		}
 	}
 }
\ No newline at end of file
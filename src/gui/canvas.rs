@@ -0,0 +1,159 @@
+﻿use crate::app::App;
+use easy_imgui::{Color, DrawFlags, Ui, Vector2, MouseButton};
+
+const ZOOM_MIN: f32 = 0.1;
+const ZOOM_MAX: f32 = 20.0;
+const ZOOM_STEP: f32 = 1.1;
+
+// CanvasView
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct CanvasView {
+	pub zoom: f32,
+	pub pan: [ f32; 2 ]
+}
+
+impl Default for CanvasView {
+	fn default() -> Self {
+		Self {
+			zoom: 1.0,
+			pan: [ 0.0, 0.0 ]
+		}
+	}
+}
+
+impl CanvasView {
+	// Position and size of an image fitted into the canvas, after zoom and pan.
+	pub fn image_rect(&self, origin: Vector2, canvas: Vector2, dimensions: [u32; 2]) -> (Vector2, Vector2) {
+		let [width, height] = dimensions.map(|x| x.max(1) as f32);
+		let fit = (canvas.x / width).min(canvas.y / height);
+		
+		let size = Vector2::new(width, height) * fit * self.zoom;
+		let pos = origin + (canvas - size) * 0.5 + Vector2::from(self.pan);
+		(pos, size)
+	}
+	
+	pub fn reset(&mut self) {
+		*self = Self::default();
+	}
+	
+	// Zooms by `steps` wheel notches, keeping `anchor` fixed on screen.
+	pub fn zoom_at(&mut self, steps: f32, anchor: Vector2, origin: Vector2, canvas: Vector2, dimensions: [u32; 2]) {
+		let (pos, size) = self.image_rect(origin, canvas, dimensions);
+		let rel = Vector2::new((anchor.x - pos.x) / size.x, (anchor.y - pos.y) / size.y);
+		
+		let zoom = (self.zoom * ZOOM_STEP.powf(steps)).clamp(ZOOM_MIN, ZOOM_MAX);
+		let new_size = size * (zoom / self.zoom);
+		let new_pos = anchor - Vector2::new(rel.x * new_size.x, rel.y * new_size.y);
+		
+		self.zoom = zoom;
+		let pan = new_pos - (origin + (canvas - new_size) * 0.5);
+		self.pan = [ pan.x, pan.y ];
+	}
+	
+	// Zooms by `steps` notches around the centre of the canvas.
+	pub fn zoom_by(&mut self, steps: f32) {
+		let zoom = (self.zoom * ZOOM_STEP.powf(steps)).clamp(ZOOM_MIN, ZOOM_MAX);
+		let ratio = zoom / self.zoom;
+		self.pan = self.pan.map(|x| x * ratio);
+		self.zoom = zoom;
+	}
+	
+	pub fn pan_by(&mut self, delta: Vector2) {
+		self.pan[0] += delta.x;
+		self.pan[1] += delta.y;
+	}
+	
+	// Wheel zooms around the cursor, middle mouse drags and double click resets.
+	pub fn handle_input(&mut self, ui: &Ui<App>, origin: Vector2, canvas: Vector2, dimensions: [u32; 2]) {
+		let io = ui.io();
+		
+		if io.MouseWheel != 0.0 {
+			let mouse = Vector2::new(io.MousePos.x, io.MousePos.y);
+			self.zoom_at(io.MouseWheel, mouse, origin, canvas, dimensions);
+		}
+		
+		if ui.is_mouse_double_clicked(MouseButton::Middle) {
+			self.reset();
+		} else if ui.is_mouse_down(MouseButton::Middle) {
+			self.pan_by(Vector2::new(io.MouseDelta.x, io.MouseDelta.y));
+		}
+	}
+}
+
+// Checkerboard
+
+pub fn draw_checkerboard(ui: &Ui<App>, pos: Vector2, size: Vector2) {
+	const CELLS: f32 = 16.0;
+	
+	let draw = ui.window_draw_list();
+	draw.add_rect_filled(pos, pos + size, Color::new(0.3, 0.3, 0.3, 1.0), 0.0, DrawFlags::None);
+	
+	// Cells scale with the canvas so zooming in never produces more of them.
+	let cell = size.x.min(size.y) / CELLS;
+	if cell <= 0.0 { return }
+	
+	let cols = (size.x / cell).ceil() as i32;
+	let rows = (size.y / cell).ceil() as i32;
+	let color = Color::new(0.4, 0.4, 0.4, 1.0);
+	
+	for row in 0..rows {
+		for col in (row % 2..cols).step_by(2) {
+			let min = pos + Vector2::new(col as f32 * cell, row as f32 * cell);
+			let max = Vector2::new((min.x + cell).min(pos.x + size.x), (min.y + cell).min(pos.y + size.y));
+			draw.add_rect_filled(min, max, color, 0.0, DrawFlags::None);
+		}
+	}
+	
+	draw.add_rect(pos, pos + size, Color::new(0.5, 0.5, 0.5, 1.0), 0.0, DrawFlags::None, 1.0);
+}
+
+// Placeholder
+
+pub fn draw_placeholder(ui: &Ui<App>, pos: Vector2, size: Vector2, dimensions: Option<[u32; 2]>) {
+	let center = pos + size * 0.5;
+	
+	let draw = ui.window_draw_list();
+	draw.add_rect_filled(pos, pos + size, Color::new(0.15, 0.15, 0.15, 1.0), 0.0, DrawFlags::None);
+	draw.add_rect(pos, pos + size, Color::new(0.4, 0.4, 0.4, 1.0), 0.0, DrawFlags::None, 1.0);
+	
+	// Spinner
+	
+	const DOTS: i32 = 8;
+	const SPIN_RADIUS: f32 = 16.0;
+	
+	let step = (ui.get_time() * DOTS as f64) as i32;
+	for i in 0..DOTS {
+		let angle = i as f32 / DOTS as f32 * std::f32::consts::TAU;
+		let dot = center + Vector2::new(angle.cos(), angle.sin()) * SPIN_RADIUS;
+		let alpha = 1.0 - (step - i).rem_euclid(DOTS) as f32 / DOTS as f32;
+		draw.add_circle_filled(dot, 3.0, Color::new(1.0, 1.0, 1.0, alpha), 12);
+	}
+	
+	if let Some([width, height]) = dimensions {
+		let text = format!("{} x {}", width, height);
+		let text_size = ui.calc_text_size(&text);
+		let text_pos = center + Vector2::new(-text_size.x * 0.5, SPIN_RADIUS * 2.0);
+		draw.add_text(text_pos, Color::WHITE, &text);
+	}
+}
+
+// Line from `from` to `to` with a filled head, stopping short of `to` by `gap`.
+pub fn draw_arrow(ui: &Ui<App>, from: Vector2, to: Vector2, gap: f32, color: Color, thickness: f32) {
+	const HEAD_LENGTH: f32 = 8.0;
+	const HEAD_WIDTH: f32 = 4.0;
+	
+	let delta = to - from;
+	let length = (delta.x * delta.x + delta.y * delta.y).sqrt();
+	if length <= gap * 2.0 + HEAD_LENGTH { return }
+	
+	let dir = delta / length;
+	let side = Vector2::new(-dir.y, dir.x) * HEAD_WIDTH;
+	let start = from + dir * gap;
+	let tip = to - dir * gap;
+	let base = tip - dir * HEAD_LENGTH;
+	
+	let draw = ui.window_draw_list();
+	draw.add_line(start, base, color, thickness);
+	draw.add_triangle_filled(tip, base + side, base - side, color);
+}
\ No newline at end of file
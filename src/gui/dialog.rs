@@ -0,0 +1,29 @@
+﻿use crate::app::App;
+use easy_imgui::{Key, Ui, WindowFlags};
+
+// Dialogs
+//
+// Pieces shared by the editor's modal dialogs.
+
+// Draws the popup `name` with `content`, opening it first when `opening` is set.
+// Returns whether it's still open, so callers can drop the dialog once it's closed.
+pub fn modal(ui: &Ui<App>, name: &str, opening: bool, content: impl FnOnce()) -> bool {
+	if opening {
+		ui.open_popup(name);
+	}
+	
+	ui.popup_modal_config(name)
+		.close_button(true)
+		.flags(WindowFlags::AlwaysAutoResize)
+		.with(content)
+		.is_some()
+}
+
+// Button closing the popup without a result, Escape presses it too.
+pub fn cancel_button(ui: &Ui<App>, label: &str) -> bool {
+	let cancel = ui.button(label) || ui.is_key_pressed(Key::Escape);
+	if cancel {
+		ui.close_current_popup();
+	}
+	cancel
+}
\ No newline at end of file
@@ -0,0 +1,196 @@
+﻿use crate::{
+	app::App,
+	schema::{
+		SchemaFile, SchemaSerializer,
+		data::SchemaData,
+		diff::{self, SchemaDiff, Status, ViewDiff}
+	}
+};
+use std::path::PathBuf;
+use easy_imgui::{Color, ColorId, Cond, FloatFormat, ItemId, TreeNodeFlags, Ui, Vector2};
+use rfd::FileDialog;
+
+pub const ADDED_COLOR: Color = Color::new(0.4, 1.0, 0.5, 1.0);
+pub const REMOVED_COLOR: Color = Color::new(1.0, 0.4, 0.4, 1.0);
+pub const CHANGED_COLOR: Color = Color::new(1.0, 0.85, 0.3, 1.0);
+
+pub fn status_color(status: Status) -> Color {
+	match status {
+		Status::Added => ADDED_COLOR,
+		Status::Removed => REMOVED_COLOR,
+		Status::Changed => CHANGED_COLOR
+	}
+}
+
+// Schema diff panel
+
+enum DiffSource {
+	// Last saved state of the open document.
+	Saved,
+	File(PathBuf, SchemaData)
+}
+
+pub struct DiffPanel {
+	pub open: bool,
+	// Draws changes to the open view over its canvas.
+	pub overlay: bool,
+	source: DiffSource,
+	// Why the last picked file couldn't be compared with.
+	error: Option<String>,
+	threshold: f32,
+	// Diff of the open document, computed once a frame while the panel or overlay needs it.
+	current: Option<SchemaDiff>
+}
+
+impl Default for DiffPanel {
+	fn default() -> Self {
+		Self {
+			open: false,
+			overlay: false,
+			source: DiffSource::Saved,
+			error: None,
+			threshold: diff::DEFAULT_THRESHOLD,
+			current: None
+		}
+	}
+}
+
+impl DiffPanel {
+	// Schema the open document is compared against.
+	pub fn base<'a>(&'a self, file: &'a SchemaFile) -> &'a SchemaData {
+		match &self.source {
+			DiffSource::Saved => file.saved_data(),
+			DiffSource::File(_, data) => data
+		}
+	}
+	
+	fn compute(&self, file: &SchemaFile) -> SchemaDiff {
+		SchemaDiff::compute(self.base(file), &file.data, self.threshold)
+	}
+	
+	// Called at the start of each frame, before the overlay and panel read the diff.
+	pub fn update(&mut self, file: Option<&SchemaFile>) {
+		self.current = match file {
+			Some(file) if self.open || self.overlay => Some(self.compute(file)),
+			_ => None
+		};
+	}
+	
+	// Changes to `view` while the overlay is shown.
+	pub fn overlay(&self, view: &str) -> Option<&ViewDiff> {
+		if !self.overlay { return None }
+		self.current.as_ref()?.views.iter()
+			.find(|x| x.name == view && x.status != Status::Removed)
+	}
+	
+	fn browse(&mut self) {
+		let path = FileDialog::new()
+			.add_filter("xml", &["xml"])
+			.pick_file();
+		
+		let Some(path) = path else { return };
+		match SchemaData::try_deserialize(path.clone()) {
+			Ok(data) => {
+				self.source = DiffSource::File(path, data);
+				self.error = None;
+			},
+			Err(err) => self.error = Some(err)
+		}
+	}
+	
+	// Draws the panel. Returns a view and bone label to select when a bone is clicked.
+	pub fn draw(&mut self, ui: &Ui<App>, file: Option<&SchemaFile>) -> Option<(String, Option<String>)> {
+		if !self.open { return None }
+		
+		let mut select = None;
+		let mut open = self.open;
+		ui.set_next_window_size(Vector2::new(450.0, 500.0), Cond::FirstUseEver);
+		ui.window_config("Schema Diff")
+			.open(&mut open)
+			.with(|| {
+				// Source
+				
+				ui.text("Compare with:");
+				if ui.radio_button_config("Saved version", matches!(self.source, DiffSource::Saved)).build() {
+					self.source = DiffSource::Saved;
+				}
+				let other = match &self.source {
+					DiffSource::File(path, _) => path.file_name().map_or(String::new(), |x| x.to_string_lossy().into_owned()),
+					DiffSource::Saved => "Other file".to_string()
+				};
+				let is_file = matches!(self.source, DiffSource::File(..));
+				if ui.radio_button_config(format!("{}##other", other), is_file).build() && !is_file {
+					self.browse();
+				}
+				ui.same_line();
+				if ui.button("Browse...") {
+					self.browse();
+				}
+				if let Some(err) = &self.error {
+					ui.text_colored(Color::new(1.0, 0.4, 0.4, 1.0), format!("Failed to read schema: {}", err));
+				}
+				
+				ui.set_next_item_width(150.0);
+				ui.drag_float_config("Move threshold", &mut self.threshold)
+					.range(0.0, 0.1)
+					.speed(0.0001)
+					.display_format(FloatFormat::F(4))
+					.build();
+				ui.checkbox("Show on canvas", &mut self.overlay);
+				
+				ui.separator();
+				
+				let Some(file) = file else {
+					ui.text_disabled("No schema loaded.");
+					return;
+				};
+				// Opened since the frame started, so not computed yet.
+				if self.current.is_none() {
+					self.current = Some(self.compute(file));
+				}
+				let Some(diff) = &self.current else { return };
+				
+				if diff.is_empty() {
+					ui.text("No differences.");
+					return;
+				}
+				ui.text(&format!("{} view(s) differ", diff.views.len()));
+				ui.same_line();
+				if ui.button("Copy as text") {
+					ui.set_clipboard_text(diff.to_string());
+				}
+				
+				// Changes
+				
+				ui.child_config("##changes").with(|| {
+					for (i, view) in diff.views.iter().enumerate() {
+						ui.with_push((ItemId(i), (ColorId::Text, status_color(view.status))), || {
+							ui.tree_node_config(view.summary())
+								.flags(TreeNodeFlags::DefaultOpen | TreeNodeFlags::SpanAvailWidth)
+								.with(|| {
+									for file in &view.images_added {
+										ui.text_colored(ADDED_COLOR, format!("+ image {}", file));
+									}
+									for file in &view.images_removed {
+										ui.text_colored(REMOVED_COLOR, format!("- image {}", file));
+									}
+									
+									// Clicking a bone that still exists selects it in the editor.
+									for (j, bone) in view.bones.iter().enumerate() {
+										ui.with_push((ItemId(j), (ColorId::Text, status_color(bone.status))), || {
+											let exists = view.status != Status::Removed && bone.status != Status::Removed;
+											if ui.selectable_config(bone.summary()).build() && exists {
+												select = Some((view.name.clone(), Some(bone.label.clone())));
+											}
+										});
+									}
+								});
+						});
+					}
+				});
+			});
+		
+		self.open = open;
+		select
+	}
+}
\ No newline at end of file
@@ -1,71 +1,206 @@
 ﻿use crate::{
-	app::App,
-	config::Configuration,
-	gui::images::ImageLoader,
+	app::{App, TITLE},
+	config::{CanvasSession, Configuration, Session},
+	gui::{
+		canvas::{self, CanvasView},
+		dialog,
+		diff::{status_color, DiffPanel},
+		history::History,
+		images::ImageLoader,
+		export::{CsvExport, PreviewExport, SvgExport},
+		import::{BoneImport, CsvImport, PoseImport, PreviewImage, SvgImport, ViewImport},
+		merge::MergeResolver,
+		theme
+	},
+	keys::Action,
+	render::{self, html, svg, RenderOptions},
 	schema::{
-		SchemaFile,
-		SchemaSerializer,
-		data::{SchemaData, SchemaView, SchemaBone, SchemaImage}
+		SchemaFile, SchemaSerializer,
+		csv,
+		data::{SchemaData, SchemaView, SchemaBone, SchemaImage, Translations},
+		diff::{Status, ViewDiff},
+		merge::SchemaMerge,
+		project::ImageLayer,
+		pose,
+		skeleton
 	}
 };
 use std::{
 	cell::RefCell,
+	collections::{HashMap, HashSet},
+	fs,
 	ops::DerefMut,
 	path::PathBuf,
 	rc::Rc
 };
-use easy_imgui::{ChildFlags, Color, Ui, Vector2, MouseButton, PopupFlags, TabItemFlags, Key};
+use easy_imgui::{ButtonFlags, ChildFlags, Color, ItemId, Ui, Vector2, MouseButton, PopupFlags, TabItemFlags, Key, WindowFlags};
 use rfd::FileDialog;
 
+const BONE_PANEL_WIDTH: f32 = 250.0;
+const NUDGE_STEP: f32 = 0.001;
+const KEY_ZOOM_STEPS: f32 = 2.0;
+
+// Labels by view index and bone index, `None` standing for the view's own name.
+type LabelSet = HashSet<(usize, Option<usize>)>;
+
 // Editor
 
 pub struct Editor {
 	pub cfg: Configuration,
 	images: Rc<RefCell<ImageLoader>>,
-	file: Option<SchemaFile>,
-	state: UiState
+	docs: Vec<Document>,
+	active: usize,
+	// Document tab to select on the next frame.
+	open_doc: Option<usize>,
+	next_id: usize,
+	clipboard: Vec<SchemaBone>,
+	pending: Option<FileAction>,
+	prompt_opening: bool,
+	translations_opening: bool,
+	// Labels found missing a translation when the panel opened, by view and bone index.
+	missing: Option<(String, LabelSet)>,
+	bone_import: Option<BoneImport>,
+	pose_import: Option<PoseImport>,
+	csv_import: Option<CsvImport>,
+	view_import: Option<ViewImport>,
+	svg_import: Option<SvgImport>,
+	csv_export: Option<CsvExport>,
+	preview_export: Option<PreviewExport>,
+	svg_export: Option<SvgExport>,
+	diff: DiffPanel,
+	merge: Option<MergeResolver>,
+	// Failures shown to the user, oldest first, until the error dialog is closed.
+	errors: Vec<String>,
+	errors_opening: bool,
+	quit: bool
+}
+
+// Actions that affect open documents. Closing and quitting need unsaved changes handled first.
+pub enum FileAction {
+	New,
+	Open,
+	OpenPath(PathBuf),
+	Close(usize),
+	Quit
 }
 
 impl Editor {
-	pub fn new(images: Rc<RefCell<ImageLoader>>) -> Self {
-		Self {
-			cfg: Configuration::open(),
+	pub fn new(images: Rc<RefCell<ImageLoader>>, cfg: Configuration) -> Self {
+		images.borrow_mut().set_budget(cfg.image_budget_bytes());
+		
+		let mut editor = Self {
+			cfg,
 			images,
-			file: None,
-			state: UiState::default()
+			docs: Vec::new(),
+			active: 0,
+			open_doc: None,
+			next_id: 0,
+			clipboard: Vec::new(),
+			pending: None,
+			prompt_opening: false,
+			translations_opening: false,
+			missing: None,
+			bone_import: None,
+			pose_import: None,
+			csv_import: None,
+			view_import: None,
+			svg_import: None,
+			csv_export: None,
+			preview_export: None,
+			svg_export: None,
+			diff: DiffPanel::default(),
+			merge: None,
+			errors: Vec::new(),
+			errors_opening: false,
+			quit: false
+		};
+		editor.restore_session();
+		if let Some(sources) = editor.cfg.merge_sources.take() {
+			editor.resolve_merge(sources);
 		}
+		editor
 	}
 	
 	// UI Draw
 	
 	pub fn draw(&mut self, ui: &Ui<App>) {
-		if self.file.is_none() {
+		// Shortcuts such as New and Open are needed most when nothing is open.
+		self.handle_keys(ui);
+		self.diff.update(self.docs.get(self.active).map(|doc| &doc.file));
+		
+		if self.docs.is_empty() {
 			ui.text("No schema loaded.");
 			return;
 		}
 		
-		self.handle_keys(ui);
+		self.draw_documents(ui);
 		
-		ui.with_group(|| {
-			self.draw_views(ui);
+		if self.active >= self.docs.len() { return }
+		
+		// Keeps widget state such as popups and tab selection apart between documents.
+		ui.with_push(ItemId(self.docs[self.active].id), || {
+			ui.with_group(|| {
+				self.draw_views(ui);
+			});
+			ui.same_line();
+			ui.with_group(|| {
+				if let Some(name) = &self.docs[self.active].state.view {
+					let active = name.clone();
+					self.draw_view(ui, active);
+				}
+			});
 		});
-		ui.same_line();
-		ui.with_group(|| {
-			if let Some(name) = &self.state.view {
-				let active = name.clone();
-				self.draw_view(ui, active);
+		
+		// Edits made while a widget is held (drags, text input) become one undo step.
+		let doc = &mut self.docs[self.active];
+		doc.history.track(&doc.file, !ui.is_any_item_active());
+	}
+	
+	fn draw_documents(&mut self, ui: &Ui<App>) {
+		let mut close_at: Option<usize> = None;
+		
+		ui.tab_bar_config("##documents").with(|| {
+			for (i, doc) in self.docs.iter().enumerate() {
+				let mut open = true;
+				let mut flags = TabItemFlags::None;
+				if doc.file.is_dirty() {
+					flags |= TabItemFlags::UnsavedDocument;
+				}
+				if self.open_doc == Some(i) {
+					flags |= TabItemFlags::SetSelected;
+					self.open_doc = None;
+				}
+				
+				ui.tab_item_config(format!("{}###doc_{}", doc.file.display_name(), doc.id))
+					.opened(&mut open)
+					.flags(flags)
+					.with(|| self.active = i);
+				
+				if !open {
+					close_at = Some(i);
+				}
 			}
 		});
+		
+		if let Some(i) = close_at {
+			self.request(FileAction::Close(i));
+		}
 	}
 	
 	fn draw_views(&mut self, ui: &Ui<App>) {
-		let file = self.file.as_mut().unwrap();
+		let doc = &mut self.docs[self.active];
+		let file = &mut doc.file;
+		let state = &mut doc.state;
 		let data = &mut file.data;
+		let project = &mut file.project;
+		let lang = self.cfg.ui.language.as_deref();
 		
 		if ui.button("Add View") {
-			let mut view = SchemaView::default();
-			view.name = format!("View {}", data.views.len() + 1);
-			self.state.open_view(&view);
+			let view = SchemaView {
+				name: format!("View {}", data.views.len() + 1),
+				..SchemaView::default()
+			};
+			state.open_view(&view);
 			data.views.push(view);
 		}
 		
@@ -73,6 +208,7 @@ impl Editor {
 		
 		// View list
 		
+		let names: Vec<String> = data.views.iter().map(|view| view.name.clone()).collect();
 		let avail = ui.get_content_region_avail();
 		ui.list_box_config("##views")
 			.size(Vector2::new(avail.x * 0.15, avail.y))
@@ -81,20 +217,43 @@ impl Editor {
 				for view in &mut data.views {
 					i += 1;
 					
-					let is_select = self.state.is_open_view(view);
-					let is_click = ui.selectable_config(format!("{}##{}", view.name, i))
+					let is_select = state.is_open_view(view);
+					let is_click = ui.selectable_config(format!("{}##{}", view.display_name(lang), i))
 						.selected(is_select)
 						.build();
 					
 					if is_click {
-						self.state.open_view(view);
+						state.open_view(view);
 					}
 					
 					ui.popup_context_item_config()
 						.str_id(format!("view_ctx_{i}"))
 						.flags(PopupFlags::MouseButtonRight)
 						.with(|| {
-							ui.input_text_config("##name", &mut view.name).build();
+							match lang {
+								Some(lang) => {
+									Self::edit_translation(ui, "##name", &view.name, &mut view.names, lang);
+								},
+								None => {
+									// Renamed once the edit is done, the project's layers are keyed by name.
+									ui.input_text_config("##name", &mut state.rename_view).build();
+									let name = state.rename_view.clone();
+									let taken = name != view.name && names.contains(&name);
+									if ui.is_item_deactivated_after_edit() {
+										if !name.is_empty() && !taken && project.rename_view(&view.name, &name) {
+											if state.is_open_view(view) {
+												state.view = Some(name.clone());
+											}
+											view.name = name;
+										}
+									} else if !ui.is_item_active() {
+										state.rename_view = view.name.clone();
+									}
+									if taken {
+										ui.text_colored(Color::new(1.0, 0.7, 0.4, 1.0), "Another view has this name.");
+									}
+								}
+							}
 							ui.set_keyboard_focus_here(0);
 						});
 				}
@@ -102,9 +261,16 @@ impl Editor {
 	}
 	
 	fn draw_view(&mut self, ui: &Ui<App>, name: String) {
-		let file = self.file.as_mut().unwrap();
+		let overlay = self.diff.overlay(&name);
+		
+		let doc = &mut self.docs[self.active];
+		let file = &mut doc.file;
+		let state = &mut doc.state;
 		let data = &mut file.data;
+		let project = &mut file.project;
 		
+		let lang = self.cfg.ui.language.as_deref();
+		let view_name = name.clone();
 		let view = data.get_view(name);
 		if view.is_none() { return }
 		
@@ -114,10 +280,7 @@ impl Editor {
 		
 		ui.with_group(|| {
 			if ui.button("Add Bone") {
-				let mut bone = SchemaBone::default();
-				bone.label = format!("Bone{}", view.bones.len());
-				bone.name = format!("bone{}", view.bones.len());
-				view.bones.push(bone);
+				view.add_bone();
 			}
 
 			ui.spacing();
@@ -127,26 +290,44 @@ impl Editor {
 				.size(Vector2::new(avail.x * 0.15, avail.y))
 				.with(|| {
 					let mut i = 0;
+					let mut remove_at: Option<usize> = None;
+					
 					for bone in &mut view.bones {
 						i += 1;
 
-						let is_select = self.state.is_selected_bone(bone);
-						let is_click = ui.selectable_config(format!("{}##{}", bone.label, i))
+						let is_select = state.is_selected_bone(bone);
+						let is_click = ui.selectable_config(format!("{}##{}", bone.display_label(lang), i))
 							.selected(is_select)
 							.build();
 						
 						if is_click {
-							self.state.select_bone(bone);
+							state.select_bone(bone);
 						}
 
 						ui.popup_context_item_config()
 							.str_id(format!("bone_ctx_{i}"))
 							.flags(PopupFlags::MouseButtonRight)
 							.with(|| {
-								ui.input_text_config("##name", &mut bone.label).build();
+								match lang {
+									Some(lang) => {
+										Self::edit_translation(ui, "##name", &bone.label, &mut bone.labels, lang);
+									},
+									None => {
+										ui.input_text_config("##name", &mut bone.label).build();
+									}
+								}
 								ui.set_keyboard_focus_here(0);
+								
+								if ui.button("Delete") {
+									remove_at = Some(i - 1);
+									ui.close_current_popup();
+								}
 							});
 					}
+					
+					if let Some(i) = remove_at {
+						view.bones.remove(i);
+					}
 				});
 		});
 		
@@ -158,36 +339,60 @@ impl Editor {
 			ui.tab_bar_config("##imgs").with(|| {
 				ui.tab_item_config("+").flags(TabItemFlags::Trailing).with(|| {});
 				if ui.is_item_clicked(MouseButton::Left) {
-					let mut img = SchemaImage::default();
-					img.file = format!("Image{}", view.images.len());
-					self.state.open_tab = Some(img.file.clone());
+					let img = SchemaImage { file: format!("Image{}", view.images.len()) };
+					state.open_tab = Some(img.file.clone());
 					view.images.push(img);
 				}
 				
-				let mut i = 0;
+				let files: Vec<String> = view.images.iter().map(|x| x.file.clone()).collect();
+				
+				let compare_label = match &state.compare.file {
+					Some(file) if files.contains(file) => format!("Compare: {}###compare", file),
+					_ => "Compare###compare".to_string()
+				};
+				if Ui::<App>::tab_item_button(compare_label, TabItemFlags::Trailing) {
+					ui.open_popup("##compare");
+				}
+				ui.popup_config("##compare").with(|| {
+					Self::draw_compare_options(ui, &mut state.compare, &files);
+				});
+				
 				let mut remove_at: Option<usize> = None;
 				
-				for img in &mut view.images {
+				for (i, img) in view.images.iter_mut().enumerate() {
 					let file = &img.file.clone();
-					let mut tab = ui.tab_item_config(format!("{}##{}", file, i));
 					
-					if let Some(open) = &self.state.open_tab {
+					let reloaded = Self::image_file(&self.cfg, &img.file)
+						.is_some_and(|path| self.images.borrow().was_reloaded(&path));
+					let label = match reloaded {
+						true => format!("{} (reloaded)###{}", file, i),
+						false => format!("{}###{}", file, i)
+					};
+					
+					let mut tab = ui.tab_item_config(label);
+					
+					if let Some(open) = &state.open_tab {
 						if img.file.eq(open) {
 							tab = tab.flags(TabItemFlags::SetSelected);
-							self.state.open_tab = None;
+							state.open_tab = None;
 						}
 					}
 						
 					tab.with(|| {
 						let style = ui.style().get();
+						
+						state.active_tab = Some(img.file.clone());
 
-						ui.input_text_config("##name", &mut self.state.rename_img).build();
+						ui.input_text_config("##name", &mut state.rename_img).build();
 						if ui.is_item_deactivated_after_edit() {
-							let name = self.state.rename_img.clone();
-							img.file = name.clone();
-							self.state.open_tab = Some(name);
+							let name = state.rename_img.clone();
+							let taken = name != img.file && files.contains(&name);
+							if !name.is_empty() && !taken && project.rename_image(&view_name, &img.file, &name) {
+								img.file = name.clone();
+								state.open_tab = Some(name);
+							}
 						} else if !ui.is_item_activated() {
-							self.state.rename_img = img.file.clone();
+							state.rename_img = img.file.clone();
 						}
 
 						ui.same_line_ex(0.0, style.ItemInnerSpacing.x);
@@ -205,19 +410,73 @@ impl Editor {
 							}
 						);
 						
+						ui.same_line_ex(0.0, style.ItemInnerSpacing.x);
+						
+						if ui.button("Layers") {
+							ui.open_popup("##layers");
+						}
+						ui.popup_config("##layers").with(|| {
+							let mut layers = project.layers(&view_name, &img.file).to_vec();
+							Self::draw_layers(ui, &mut layers);
+							project.set_layers(&view_name, &img.file, layers);
+						});
+						
+						let base = CanvasImage::new(
+							&self.cfg, &img.file,
+							project.layers(&view_name, &img.file), 1.0
+						);
+						
+						// The compared tab must belong to this view and differ from the open one.
+						let compare = files.iter()
+							.find(|file| **file != img.file && state.compare.file.as_ref() == Some(file))
+							.map(|file| CanvasImage::new(
+								&self.cfg, file,
+								project.layers(&view_name, file), state.compare.opacity
+							));
+						
+						let tab = CanvasTab {
+							key: (view_name.clone(), img.file.clone()),
+							base,
+							compare,
+							blank: self.cfg.blank_aspect,
+							notice: None,
+							marker: MarkerStyle::new(&self.cfg),
+							diff: overlay
+						};
+						
 						let mut images = self.images.borrow_mut();
-						Self::draw_view_img(&mut self.state, ui, images.deref_mut(), &self.cfg, img);
+						Self::draw_view_img(state, ui, images.deref_mut(), tab, &mut view.bones);
 						ui.same_line();
-						Self::draw_view_bones(&mut self.state, ui, &mut view.bones);
+						Self::draw_view_bones(state, ui, &mut view.bones, lang);
 					});
-					
-					i += 1;
 				}
 				
 				if let Some(i) = remove_at {
-					view.images.remove(i);
+					let img = view.images.remove(i);
+					if !view.images.iter().any(|x| x.file == img.file) {
+						project.remove_image(&view_name, &img.file);
+					}
 				}
 			});
+			
+			// Views without images still get a blank canvas to lay bones out on.
+			
+			if view.images.is_empty() {
+				let tab = CanvasTab {
+					key: (view_name.clone(), String::new()),
+					base: CanvasImage::blank(),
+					compare: None,
+					blank: self.cfg.blank_aspect,
+					notice: Some("No images in this view.".to_string()),
+					marker: MarkerStyle::new(&self.cfg),
+					diff: overlay
+				};
+				
+				let mut images = self.images.borrow_mut();
+				Self::draw_view_img(state, ui, images.deref_mut(), tab, &mut view.bones);
+				ui.same_line();
+				Self::draw_view_bones(state, ui, &mut view.bones, lang);
+			}
 		});
 	}
 	
@@ -225,59 +484,263 @@ impl Editor {
 		state: &mut UiState,
 		ui: &Ui<App>,
 		images: &mut ImageLoader,
-		cfg: &Configuration,
-		img: &mut SchemaImage
+		tab: CanvasTab<'_>,
+		bones: &mut [SchemaBone]
 	) {
-		if cfg.image_path.is_none() { return }
+		let CanvasTab { key, base, compare, blank, notice, marker, diff } = tab;
 		
-		let path = cfg.image_path.clone().unwrap();
-		let path = PathBuf::from(path).join(&img.file);
-		if !path.exists() {
-			ui.text("File does not exist.");
-			return;
+		// Falls back to a blank canvas when there's no image to show.
+		
+		let (dimensions, notice) = match &base.path {
+			_ if notice.is_some() => (blank, notice),
+			None => (blank, Some("Image path not set.".to_string())),
+			Some(path) if !path.exists() => (blank, Some(format!("File does not exist: {}", path.display()))),
+			Some(path) => {
+				let data = images.load(path.clone());
+				match data.error() {
+					Some(err) => (blank, Some(format!("Failed to load image: {}", err))),
+					// Until the header is read the placeholder is laid out as a square.
+					None => (data.dimensions.unwrap_or([1, 1]), None)
+				}
+			}
+		};
+		let notice = notice.as_deref();
+		let is_blank = notice.is_some();
+		
+		let split = compare.is_some() && state.compare.mode == CompareMode::Split;
+		let panes = if split { 2.0 } else { 1.0 };
+		
+		let avail = ui.get_content_region_avail();
+		let fit_width = avail.y * dimensions[0] as f32 / dimensions[1].max(1) as f32;
+		let width = (fit_width * panes).min(avail.x - BONE_PANEL_WIDTH).max(1.0) / panes;
+		let size = Vector2::new(width, avail.y);
+		
+		// Zoom and pan are stored per tab, split panes share the same transform.
+		let mut transform = state.canvas.get(&key).copied().unwrap_or_default();
+		
+		ui.with_group(|| {
+			match compare {
+				Some(other) if split => {
+					let pane = CanvasPane { id: "##canvas", size, dimensions, is_blank, notice, marker, diff, stack: &[base] };
+					Self::draw_canvas(state, ui, images, pane, &mut transform, bones);
+					ui.same_line_ex(0.0, 0.0);
+					// The compared image is laid out by its own size, so bones line up with it.
+					let dimensions = other.path.clone()
+						.filter(|path| path.exists())
+						.and_then(|path| images.load(path).dimensions)
+						.unwrap_or(blank);
+					let pane = CanvasPane { id: "##compare", size, dimensions, is_blank: false, notice: None, marker, diff, stack: &[other] };
+					Self::draw_canvas(state, ui, images, pane, &mut transform, bones);
+				},
+				Some(other) => {
+					let pane = CanvasPane { id: "##canvas", size, dimensions, is_blank, notice, marker, diff, stack: &[base, other] };
+					Self::draw_canvas(state, ui, images, pane, &mut transform, bones);
+				},
+				None => {
+					let pane = CanvasPane { id: "##canvas", size, dimensions, is_blank, notice, marker, diff, stack: &[base] };
+					Self::draw_canvas(state, ui, images, pane, &mut transform, bones);
+				}
+			}
+		});
+		
+		state.canvas.insert(key, transform);
+	}
+	
+	fn draw_canvas(
+		state: &mut UiState,
+		ui: &Ui<App>,
+		images: &mut ImageLoader,
+		pane: CanvasPane,
+		transform: &mut CanvasView,
+		bones: &mut [SchemaBone]
+	) {
+		ui.child_config(pane.id)
+			.size(pane.size)
+			.child_flags(ChildFlags::Border)
+			.window_flags(WindowFlags::NoScrollbar | WindowFlags::NoScrollWithMouse)
+			.with(|| {
+				let origin = ui.get_cursor_screen_pos();
+				let canvas = ui.get_content_region_avail();
+				
+				// Catches middle mouse input so dragging doesn't move the window.
+				ui.invisible_button_config("##input")
+					.size(canvas)
+					.flags(ButtonFlags::MouseButtonMiddle)
+					.build();
+				if ui.is_item_hovered() || ui.is_item_active() {
+					transform.handle_input(ui, origin, canvas, pane.dimensions);
+				}
+				
+				let (pos, size) = transform.image_rect(origin, canvas, pane.dimensions);
+				state.img_cursor = [ pos.x, pos.y ];
+				state.img_size = [ size.x, size.y ];
+				
+				let draw = ui.window_draw_list();
+				let uv0 = Vector2::new(0.0, 0.0);
+				let uv1 = Vector2::new(1.0, 1.0);
+				
+				for (i, img) in pane.stack.iter().enumerate() {
+					if i == 0 && pane.is_blank {
+						canvas::draw_checkerboard(ui, pos, size);
+					} else if let Some(path) = img.path.clone() {
+						let data = images.load(path);
+						match data.texture_id() {
+							Some(tex) => {
+								let tint = Color::new(1.0, 1.0, 1.0, img.opacity);
+								draw.add_image(tex, pos, pos + size, uv0, uv1, tint);
+							},
+							None if i == 0 => {
+								canvas::draw_placeholder(ui, pos, size, data.dimensions);
+							},
+							None => ()
+						}
+					}
+					
+					// Overlay layers are stretched over their image in order.
+					
+					for (path, opacity) in &img.layers {
+						if let Some(tex) = images.load(path.clone()).texture_id() {
+							let tint = Color::new(1.0, 1.0, 1.0, opacity * img.opacity);
+							draw.add_image(tex, pos, pos + size, uv0, uv1, tint);
+						}
+					}
+				}
+				
+				if let Some(notice) = pane.notice {
+					let style = ui.style().get();
+					let text_pos = origin + Vector2::new(style.WindowPadding.x, style.WindowPadding.y);
+					draw.add_text(text_pos, Color::new(1.0, 0.7, 0.4, 1.0), notice);
+				}
+				
+				if let Some(diff) = pane.diff {
+					Self::draw_diff_overlay(state, ui, bones, diff, pane.marker);
+				}
+				Self::draw_bone_markers(state, ui, bones, pane.marker);
+				if let Some(diff) = pane.diff {
+					Self::draw_diff_legend(ui, origin, canvas, diff);
+				}
+			});
+	}
+	
+	fn draw_compare_options(ui: &Ui<App>, compare: &mut CompareState, files: &[String]) {
+		let preview = compare.file.clone().unwrap_or_else(|| "None".to_string());
+		ui.set_next_item_width(200.0);
+		ui.combo_config("Tab").preview_value(preview).with(|| {
+			if ui.selectable_config("None").selected(compare.file.is_none()).build() {
+				compare.file = None;
+			}
+			for (i, file) in files.iter().enumerate() {
+				let is_select = compare.file.as_ref() == Some(file);
+				if ui.selectable_config(format!("{}##{}", file, i)).selected(is_select).build() {
+					compare.file = Some(file.clone());
+				}
+			}
+		});
+		
+		if ui.radio_button_config("Overlay", compare.mode == CompareMode::Overlay).build() {
+			compare.mode = CompareMode::Overlay;
+		}
+		ui.same_line();
+		if ui.radio_button_config("Side by side", compare.mode == CompareMode::Split).build() {
+			compare.mode = CompareMode::Split;
 		}
 		
-		let data = images.load(ui, path);
-		if let Some(rect) = data.rect {
-			let img = data.image.clone().unwrap();
-			let scale = ui.get_content_region_avail().y / img.height() as f32;
-			state.img_cursor = ui.get_cursor_screen_pos().into();
-			state.img_size = [ img.width() as f32 * scale, img.height() as f32 * scale ];
-			ui.image_with_custom_rect_config(rect, scale).build();
+		ui.with_disabled(compare.mode != CompareMode::Overlay, || {
+			ui.set_next_item_width(200.0);
+			ui.slider_float_config("Opacity", &mut compare.opacity).range(0.0, 1.0).build();
+		});
+	}
+	
+	fn draw_layers(ui: &Ui<App>, layers: &mut Vec<ImageLayer>) {
+		if layers.is_empty() {
+			ui.text_disabled("No overlay layers.");
+		}
+		
+		let mut remove_at: Option<usize> = None;
+		
+		for (i, layer) in layers.iter_mut().enumerate() {
+			ui.with_push(ItemId(i), || {
+				ui.checkbox("##visible", &mut layer.visible);
+				ui.same_line();
+				ui.set_next_item_width(200.0);
+				ui.input_text_config("##file", &mut layer.file).build();
+				ui.same_line();
+				ui.set_next_item_width(120.0);
+				ui.slider_float_config("##opacity", &mut layer.opacity).range(0.0, 1.0).build();
+				ui.same_line();
+				if ui.button("X") {
+					remove_at = Some(i);
+				}
+			});
+		}
+		
+		if let Some(i) = remove_at {
+			layers.remove(i);
+		}
+		
+		if ui.button("Add Layer") {
+			layers.push(ImageLayer::default());
+		}
+	}
+	
+	// Edits the translation into `lang`, showing the fallback as a hint. Clearing it removes it.
+	fn edit_translation(ui: &Ui<App>, label: &str, fallback: &str, translations: &mut Translations, lang: &str) -> bool {
+		let mut text = translations.get(lang).cloned().unwrap_or_default();
+		let changed = ui.input_text_hint_config(label, fallback, &mut text).build();
+		if changed {
+			if text.is_empty() {
+				translations.remove(lang);
+			} else {
+				translations.insert(lang.to_string(), text);
+			}
 		}
+		changed
+	}
+	
+	fn image_file(cfg: &Configuration, file: &str) -> Option<PathBuf> {
+		let root = cfg.image_root()?;
+		Some(PathBuf::from(root).join(file))
 	}
 	
 	fn draw_view_bones(
 		state: &mut UiState,
 		ui: &Ui<App>,
-		bones: &mut Vec<SchemaBone>
+		bones: &mut [SchemaBone],
+		lang: Option<&str>
 	) {
 		if state.bone.is_none() { return }
 		
 		if let Some(bone) = bones.iter_mut().find(|x| state.is_selected_bone(x)) {
 			ui.child_config("##bone")
+				.size(Vector2::new(BONE_PANEL_WIDTH, 0.0))
 				.child_flags(ChildFlags::Border | ChildFlags::AutoResizeY)
 				.with(|| {
-					ui.text(&bone.label);
+					ui.text(bone.display_label(lang));
 					ui.input_text_config("Bone", &mut bone.name).build();
 					ui.drag_float_config("X", &mut bone.x).range(0.0, 1.0).speed(0.001).build();
 					ui.drag_float_config("Y", &mut bone.y).range(0.0, 1.0).speed(0.001).build();
 				});
 		}
-		
-		const RADIUS: f32 = 10.0;
-		
+	}
+	
+	fn draw_bone_markers(
+		state: &mut UiState,
+		ui: &Ui<App>,
+		bones: &mut [SchemaBone],
+		marker: MarkerStyle
+	) {		
 		let mouse_pos = ui.io().MousePos;
+		let radius = marker.radius;
 		
 		let draw = ui.window_draw_list();
-		for bone in bones {
+		for bone in bones.iter() {
 			let pos = Vector2::from(state.img_cursor)
 				+ Vector2::new(state.img_size[0] * bone.x, state.img_size[1] * bone.y);
 			
-			let hover = mouse_pos.x >= pos.x - RADIUS
-				&& mouse_pos.x <= pos.x + RADIUS
-				&& mouse_pos.y >= pos.y - RADIUS
-				&& mouse_pos.y <= pos.y + RADIUS;
+			let hover = mouse_pos.x >= pos.x - radius
+				&& mouse_pos.x <= pos.x + radius
+				&& mouse_pos.y >= pos.y - radius
+				&& mouse_pos.y <= pos.y + radius;
 			
 			if hover && ui.is_mouse_clicked(MouseButton::Left) {
 				state.select_bone(bone);
@@ -285,84 +748,1259 @@ impl Editor {
 			
 			let active = hover || state.is_selected_bone(bone);
 			
-			let color = if active { Color::WHITE } else { Color::new(1.0, 1.0, 1.0, 0.65) };
+			let color = if active { marker.selected } else { marker.color };
 			let thick = if active { 2.5 } else { 1.5 };
 			
-			draw.add_circle_filled(pos, RADIUS, color, 32);
-			draw.add_circle(pos, RADIUS, Color::BLACK, 32, thick);
+			draw.add_circle_filled(pos, radius, color, 32);
+			draw.add_circle(pos, radius, Color::BLACK, 32, thick);
+		}
+	}
+	
+	// Old positions and status rings, drawn under the bone markers.
+	fn draw_diff_overlay(
+		state: &UiState,
+		ui: &Ui<App>,
+		bones: &[SchemaBone],
+		diff: &ViewDiff,
+		marker: MarkerStyle
+	) {
+		let to_screen = |[ x, y ]: [f32; 2]| Vector2::from(state.img_cursor)
+			+ Vector2::new(state.img_size[0] * x, state.img_size[1] * y);
+		let radius = marker.radius;
+		
+		let draw = ui.window_draw_list();
+		for change in &diff.bones {
+			let color = status_color(change.status);
+			let ghost = Color::new(color.r, color.g, color.b, 0.6);
+			
+			// Bones are followed by name so the overlay keeps up with edits.
+			let current = bones.iter()
+				.find(|bone| bone.name == change.name)
+				.map(|bone| to_screen([ bone.x, bone.y ]));
+			
+			if let Some(from) = change.from.map(to_screen) {
+				draw.add_circle(from, radius, ghost, 32, 1.5);
+				match (change.status, current) {
+					(Status::Removed, _) => {
+						let d = radius * 0.6;
+						draw.add_line(from - Vector2::new(d, d), from + Vector2::new(d, d), ghost, 1.5);
+						draw.add_line(from - Vector2::new(d, -d), from + Vector2::new(d, -d), ghost, 1.5);
+					},
+					(_, Some(to)) => canvas::draw_arrow(ui, from, to, radius + 2.0, ghost, 1.5),
+					(_, None) => ()
+				}
+			}
+			
+			if let Some(pos) = current {
+				draw.add_circle(pos, radius + 3.0, color, 32, 2.0);
+			}
+		}
+	}
+	
+	fn draw_diff_legend(ui: &Ui<App>, origin: Vector2, canvas: Vector2, diff: &ViewDiff) {
+		let count = |status: Status| diff.bones.iter().filter(|x| x.status == status).count();
+		let lines = [
+			(Status::Added, format!("{} added", count(Status::Added))),
+			(Status::Changed, format!("{} changed", count(Status::Changed))),
+			(Status::Removed, format!("{} removed", count(Status::Removed)))
+		];
+		
+		let style = ui.style().get();
+		let line_height = ui.get_text_line_height_with_spacing();
+		let mut pos = origin + Vector2::new(
+			style.WindowPadding.x,
+			canvas.y - style.WindowPadding.y - line_height * lines.len() as f32
+		);
+		
+		let draw = ui.window_draw_list();
+		for (status, text) in lines {
+			draw.add_text(pos, status_color(status), &text);
+			pos.y += line_height;
 		}
 	}
 	
 	// Keys
 	
 	fn handle_keys(&mut self, ui: &Ui<App>) {
-		if ui.is_key_down(Key::ModCtrl) && ui.is_key_pressed(Key::S) {
-			self.save_file(false);
+		// Popups and menus take the keyboard, including the shortcut editor.
+		if ui.is_popup_open_ex(None, PopupFlags::AnyPopup) { return }
+		
+		let typing = ui.io().WantTextInput;
+		for action in self.cfg.keys.pressed(ui) {
+			if typing && !action.is_global() { continue }
+			self.run_action(action);
 		}
 	}
 	
-	// Data
+	// Actions
 	
-	pub fn is_file_loaded(&self) -> bool {
-		self.file.is_some()
+	pub fn run_action(&mut self, action: Action) {
+		match action {
+			Action::New => self.request(FileAction::New),
+			Action::Open => self.request(FileAction::Open),
+			Action::Close => self.request(FileAction::Close(self.active)),
+			Action::Save => { self.save_file(false); },
+			Action::SaveAs => { self.save_file(true); },
+			Action::Copy => self.copy_bones(),
+			Action::Paste => self.paste_bones(),
+			_ => if let Some(doc) = self.docs.get_mut(self.active) {
+				doc.run_action(action);
+			}
+		}
 	}
 	
-	pub fn open_file(&mut self) {
+	pub fn can_undo(&self) -> bool {
+		self.doc().is_some_and(|doc| doc.history.can_undo())
+	}
+	
+	pub fn can_redo(&self) -> bool {
+		self.doc().is_some_and(|doc| doc.history.can_redo())
+	}
+	
+	pub fn has_view(&self) -> bool {
+		self.doc().is_some_and(|doc| doc.state.view.is_some())
+	}
+	
+	pub fn has_bone(&self) -> bool {
+		self.doc().is_some_and(|doc| doc.state.view.is_some() && doc.state.bone.is_some())
+	}
+	
+	// Languages
+	
+	// Translated languages in the open schema, including the selected one.
+	pub fn languages(&self) -> Vec<String> {
+		let mut languages = self.doc().map(|doc| doc.file.data.languages()).unwrap_or_default();
+		languages.extend(self.cfg.ui.language.clone());
+		languages.into_iter().collect()
+	}
+	
+	pub fn fallback_language(&self) -> Option<String> {
+		self.doc().and_then(|doc| doc.file.data.language.clone())
+	}
+	
+	pub fn set_language(&mut self, lang: Option<String>) {
+		// Picking the fallback language just shows the untranslated labels.
+		self.cfg.ui.language = lang.filter(|lang| Some(lang) != self.fallback_language().as_ref());
+	}
+	
+	pub fn open_translations(&mut self) {
+		self.translations_opening = true;
+	}
+	
+	fn draw_translations(&mut self, ui: &Ui<App>) {
+		const POPUP_NAME: &str = "Missing translations";
+		
+		let opening = std::mem::take(&mut self.translations_opening);
+		if opening {
+			self.missing = None;
+		}
+		
+		// Applied once the dialog is drawn, the document is borrowed until then.
+		let mut picked = None;
+		dialog::modal(ui, POPUP_NAME, opening, || {
+			let languages = self.languages();
+			let Some(doc) = self.docs.get_mut(self.active) else {
+				ui.text_disabled("No schema loaded.");
+				return;
+			};
+			
+			// Language the untranslated labels are in, saved as `<Views lang>`.
+			let mut fallback = doc.file.data.language.clone().unwrap_or_default();
+			ui.set_next_item_width(200.0);
+			if ui.input_text_hint_config("Schema language", "Not set", &mut fallback).build() {
+				let fallback = fallback.trim();
+				doc.file.data.language = (!fallback.is_empty()).then(|| fallback.to_string());
+			}
+			
+			let preview = self.cfg.ui.language.clone().unwrap_or_else(|| "Select...".to_string());
+			ui.set_next_item_width(200.0);
+			ui.combo_config("Language").preview_value(preview).with(|| {
+				for lang in &languages {
+					let is_select = self.cfg.ui.language.as_ref() == Some(lang);
+					if ui.selectable_config(lang.as_str()).selected(is_select).build() {
+						picked = Some(lang.clone());
+					}
+				}
+			});
+			
+			let Some(lang) = self.cfg.ui.language.clone() else {
+				ui.text_disabled("Select a language to list its missing labels.");
+				return;
+			};
+			
+			let views = &mut doc.file.data.views;
+			let missing = views.iter()
+				.map(|view| !view.names.contains_key(&lang) as usize
+					+ view.bones.iter().filter(|bone| !bone.labels.contains_key(&lang)).count())
+				.sum::<usize>();
+			
+			match missing {
+				0 => ui.text(&format!("Everything is translated into {}.", lang)),
+				n => ui.text(&format!("{} label(s) missing a {} translation.", n, lang))
+			}
+			
+			// The list is kept from when the panel opened, so entries don't vanish while typing.
+			if self.missing.as_ref().is_none_or(|(missing_lang, _)| *missing_lang != lang) {
+				self.missing = Some((lang.clone(), Self::find_missing(views, &lang)));
+			}
+			let Some((_, listed)) = &self.missing else { return };
+			
+			ui.child_config("##missing")
+				.size(Vector2::new(500.0, 400.0))
+				.child_flags(ChildFlags::Border)
+				.with(|| {
+					for (i, view) in views.iter_mut().enumerate() {
+						let is_listed = |bone: Option<usize>| listed.contains(&(i, bone));
+						if !is_listed(None) && !(0..view.bones.len()).any(|j| is_listed(Some(j))) { continue }
+						
+						ui.with_push(ItemId(i), || {
+							ui.separator_text(&view.name);
+							if is_listed(None) {
+								ui.set_next_item_width(250.0);
+								Self::edit_translation(ui, "View", &view.name, &mut view.names, &lang);
+							}
+							
+							for (j, bone) in view.bones.iter_mut().enumerate() {
+								if !is_listed(Some(j)) { continue }
+								ui.with_push(ItemId(j), || {
+									ui.set_next_item_width(250.0);
+									Self::edit_translation(ui, &bone.label, &bone.label, &mut bone.labels, &lang);
+								});
+							}
+						});
+					}
+				});
+		});
+		
+		if let Some(lang) = picked {
+			self.set_language(Some(lang));
+		}
+	}
+	
+	fn find_missing(views: &[SchemaView], lang: &str) -> LabelSet {
+		let mut missing = HashSet::new();
+		for (i, view) in views.iter().enumerate() {
+			if !view.names.contains_key(lang) {
+				missing.insert((i, None));
+			}
+			for (j, bone) in view.bones.iter().enumerate() {
+				if !bone.labels.contains_key(lang) {
+					missing.insert((i, Some(j)));
+				}
+			}
+		}
+		missing
+	}
+	
+	// Import
+	
+	pub fn import_bone_list(&mut self) {
+		if !self.has_view() { return }
+		
 		let path = FileDialog::new()
-			.add_filter("xml", &["xml"])
-			.set_directory("/")
+			.add_filter("Bone lists", &["txt", "json", "xml"])
+			.add_filter("All files", &["*"])
 			.pick_file();
 		
 		if let Some(path) = path {
-			let data = SchemaData::deserialize(path.clone());
-			let file = SchemaFile {
-				path: Some(path),
-				data
-			};
-			self.file = Some(file);
+			self.bone_import = Some(BoneImport::open(path));
 		}
 	}
 	
-	pub fn save_file(&mut self, new_path: bool) {
-		if self.file.is_none() { return }
-
-		println!("Saving...");
-
-		let file = self.file.as_mut().unwrap();
-
-		if file.path.is_none() || new_path {
-			file.path = FileDialog::new()
-				.add_filter("xml", &["xml"])
-				.save_file();
+	fn draw_bone_import(&mut self, ui: &Ui<App>) {
+		let Some(import) = self.bone_import.as_mut() else { return };
+		let Some(doc) = self.docs.get_mut(self.active) else {
+			self.bone_import = None;
+			return;
+		};
+		let Some(view) = doc.current_view() else {
+			self.bone_import = None;
+			return;
+		};
+		
+		let mut open = true;
+		if let Some(names) = import.draw(ui, view, &mut open) {
+			let added = skeleton::add_bones(view, &names);
+			println!("Imported {} bone(s).", added.len());
+			if let Some(label) = added.into_iter().next() {
+				doc.state.bone = Some(label);
+			}
 		}
-
-		if let Some(path) = &file.path {
-			file.write(path);
-		} else {
-			println!("Save cancelled");
+		if !open {
+			self.bone_import = None;
 		}
 	}
-
-	pub fn new_file(&mut self) {
-		println!("Creating new schema.");
-		self.file = Some(SchemaFile::default());
+	
+	pub fn import_pose(&mut self) {
+		if !self.has_view() { return }
+		
+		let path = FileDialog::new()
+			.add_filter("json", &["json"])
+			.pick_file();
+		
+		if let Some(path) = path {
+			self.pose_import = Some(PoseImport::open(path));
+		}
 	}
 	
-	pub fn save_config(&mut self) {
-		self.cfg.save();
+	fn draw_pose_import(&mut self, ui: &Ui<App>) {
+		if self.pose_import.is_none() { return }
+		
+		// The open tab's image is shown behind the preview, so a render from the same camera lines up.
+		let image = self.preview_image();
+		
+		let Some(import) = self.pose_import.as_mut() else { return };
+		let Some(doc) = self.docs.get_mut(self.active) else {
+			self.pose_import = None;
+			return;
+		};
+		let Some(view) = doc.current_view() else {
+			self.pose_import = None;
+			return;
+		};
+		
+		let mut open = true;
+		if let Some(positions) = import.draw(ui, view, &image, &mut open) {
+			let (updated, added) = pose::apply(view, &positions);
+			println!("Moved {} and added {} bone(s) from pose.", updated, added);
+		}
+		if !open {
+			self.pose_import = None;
+		}
 	}
-}
-
-// State
-
+	
+	pub fn import_csv(&mut self) {
+		if !self.is_file_loaded() { return }
+		
+		let path = FileDialog::new()
+			.add_filter("csv", &["csv"])
+			.pick_file();
+		
+		if let Some(path) = path {
+			self.csv_import = Some(CsvImport::open(path, self.preview_image().dimensions));
+		}
+	}
+	
+	fn draw_csv_import(&mut self, ui: &Ui<App>) {
+		let Some(import) = self.csv_import.as_mut() else { return };
+		let Some(doc) = self.docs.get_mut(self.active) else {
+			self.csv_import = None;
+			return;
+		};
+		
+		let mut open = true;
+		if let Some(changes) = import.draw(ui, &doc.file.data, &mut open) {
+			csv::apply(&mut doc.file.data, &changes);
+			println!("Applied {} change(s) from CSV.", changes.len());
+		}
+		if !open {
+			self.csv_import = None;
+		}
+	}
+	
+	pub fn import_views(&mut self) {
+		if !self.is_file_loaded() { return }
+		
+		let path = FileDialog::new()
+			.add_filter("xml", &["xml"])
+			.pick_file();
+		
+		if let Some(path) = path {
+			self.view_import = Some(ViewImport::open(path));
+		}
+	}
+	
+	fn draw_view_import(&mut self, ui: &Ui<App>) {
+		let Some(import) = self.view_import.as_ref() else { return };
+		let file = import.focused().and_then(|view| view.images.first()).map(|img| img.file.clone());
+		let image = self.image_preview(file.as_deref());
+		
+		let Some(import) = self.view_import.as_mut() else { return };
+		let Some(doc) = self.docs.get_mut(self.active) else {
+			self.view_import = None;
+			return;
+		};
+		
+		let mut open = true;
+		if let Some((views, merge)) = import.draw(ui, &doc.file.data, &image, &mut open) {
+			println!("Importing {} view(s).", views.len());
+			for view in views {
+				doc.file.data.import_view(view, merge);
+			}
+		}
+		if !open {
+			self.view_import = None;
+		}
+	}
+	
+	pub fn import_svg(&mut self) {
+		let Some(doc) = self.docs.get(self.active) else { return };
+		
+		let path = FileDialog::new()
+			.add_filter("svg", &["svg"])
+			.pick_file();
+		
+		if let Some(path) = path {
+			self.svg_import = Some(SvgImport::open(path, &doc.file.data, doc.state.view.as_deref()));
+		}
+	}
+	
+	fn draw_svg_import(&mut self, ui: &Ui<App>) {
+		let Some(import) = self.svg_import.as_mut() else { return };
+		let Some(doc) = self.docs.get_mut(self.active) else {
+			self.svg_import = None;
+			return;
+		};
+		
+		let mut open = true;
+		if let Some((view, moves)) = import.draw(ui, &doc.file.data, &mut open) {
+			if let Some(view) = doc.file.data.get_view(view) {
+				let moved = svg::apply(view, &moves);
+				println!("Moved {} bone(s) from SVG.", moved);
+			}
+		}
+		if !open {
+			self.svg_import = None;
+		}
+	}
+	
+	// Export
+	
+	pub fn export_csv(&mut self) {
+		if !self.is_file_loaded() { return }
+		self.csv_export = Some(CsvExport::open(self.preview_image().dimensions));
+	}
+	
+	fn draw_csv_export(&mut self, ui: &Ui<App>) {
+		let Some(export) = self.csv_export.as_mut() else { return };
+		let Some(doc) = self.docs.get(self.active) else {
+			self.csv_export = None;
+			return;
+		};
+		
+		let mut open = true;
+		let mut error = None;
+		let view = doc.state.view.as_deref();
+		if let Some(content) = export.draw(ui, &doc.file.data, view, &mut open) {
+			let name = view.map_or_else(|| doc.file.display_name(), |view| view.to_string());
+			let path = FileDialog::new()
+				.add_filter("csv", &["csv"])
+				.set_file_name(format!("{}.csv", name.trim_end_matches(".xml")))
+				.save_file();
+			
+			if let Some(path) = path {
+				match fs::write(&path, content) {
+					Ok(_) => println!("Exported CSV to {}", path.display()),
+					Err(err) => error = Some(format!("Failed to export CSV: {}", err))
+				}
+			}
+		}
+		if !open {
+			self.csv_export = None;
+		}
+		if let Some(error) = error {
+			self.report(error);
+		}
+	}
+	
+	pub fn export_svg(&mut self) {
+		if !self.has_view() { return }
+		self.svg_export = Some(SvgExport::open());
+	}
+	
+	fn draw_svg_export(&mut self, ui: &Ui<App>) {
+		let Some(export) = self.svg_export.as_mut() else { return };
+		let Some((doc, view)) = self.docs.get(self.active)
+			.and_then(|doc| Some((doc, doc.file.data.views.iter().find(|view| Some(&view.name) == doc.state.view.as_ref())?)))
+		else {
+			self.svg_export = None;
+			return;
+		};
+		
+		let mut open = true;
+		let mut error = None;
+		if let Some(embed) = export.draw(ui, &view.name, &mut open) {
+			let path = FileDialog::new()
+				.add_filter("svg", &["svg"])
+				.set_file_name(format!("{}.svg", view.name))
+				.save_file();
+			
+			if let Some(path) = path {
+				let saved = svg::export(view, &self.cfg, doc.state.active_tab.as_deref(), embed)
+					.and_then(|content| fs::write(&path, content).map_err(|err| err.to_string()));
+				match saved {
+					Ok(_) => println!("Exported SVG to {}", path.display()),
+					Err(err) => error = Some(format!("Failed to export SVG: {}", err))
+				}
+			}
+		}
+		if !open {
+			self.svg_export = None;
+		}
+		if let Some(error) = error {
+			self.report(error);
+		}
+	}
+	
+	pub fn export_html(&mut self) {
+		let Some(doc) = self.docs.get(self.active) else { return };
+		let name = doc.file.display_name();
+		let name = name.trim_end_matches(".xml");
+		
+		let path = FileDialog::new()
+			.add_filter("html", &["html", "htm"])
+			.set_file_name(format!("{}.html", name))
+			.save_file();
+		
+		let Some(path) = path else { return };
+		let content = html::export(&doc.file.data, name, &self.cfg);
+		match fs::write(&path, content) {
+			Ok(_) => println!("Exported HTML to {}", path.display()),
+			Err(err) => self.report(format!("Failed to export HTML: {}", err))
+		}
+	}
+	
+	pub fn export_preview(&mut self) {
+		if !self.has_view() { return }
+		self.preview_export = Some(PreviewExport::open());
+	}
+	
+	fn draw_preview_export(&mut self, ui: &Ui<App>) {
+		let Some(export) = self.preview_export.as_mut() else { return };
+		let Some((doc, view)) = self.docs.get(self.active)
+			.and_then(|doc| Some((doc, doc.file.data.views.iter().find(|view| Some(&view.name) == doc.state.view.as_ref())?)))
+		else {
+			self.preview_export = None;
+			return;
+		};
+		
+		let mut open = true;
+		let mut error = None;
+		if let Some(preview) = export.draw(ui, &view.name, &mut open) {
+			let mut options = RenderOptions::new(&self.cfg, &doc.file.project, view, doc.state.active_tab.as_deref());
+			options.labels = preview.labels;
+			options.hit_map = preview.hit_map;
+			options.hierarchy = preview.hierarchy;
+			
+			let path = FileDialog::new()
+				.add_filter("png", &["png"])
+				.set_file_name(format!("{}.png", view.name))
+				.save_file();
+			
+			if let Some(path) = path {
+				let saved = render::render(view, &options)
+					.and_then(|img| img.save(&path).map_err(|err| err.to_string()));
+				match saved {
+					Ok(_) => println!("Exported preview to {}", path.display()),
+					Err(err) => error = Some(format!("Failed to export preview: {}", err))
+				}
+			}
+		}
+		if !open {
+			self.preview_export = None;
+		}
+		if let Some(error) = error {
+			self.report(error);
+		}
+	}
+	
+	// Diff
+	
+	pub fn open_diff(&mut self) {
+		self.diff.open = true;
+	}
+	
+	pub fn diff_overlay(&self) -> bool {
+		self.diff.overlay
+	}
+	
+	pub fn toggle_diff_overlay(&mut self) {
+		self.diff.overlay = !self.diff.overlay;
+	}
+	
+	fn draw_diff(&mut self, ui: &Ui<App>) {
+		let file = self.docs.get(self.active).map(|doc| &doc.file);
+		let Some((view, bone)) = self.diff.draw(ui, file) else { return };
+		
+		if let Some(doc) = self.docs.get_mut(self.active) {
+			doc.state.view = Some(view);
+			doc.state.bone = bone;
+		}
+	}
+	
+	// Merge
+	
+	// Merges another version of the open schema, with the schema both started from as base.
+	pub fn merge_file(&mut self) {
+		let Some(doc) = self.docs.get(self.active) else { return };
+		
+		let pick = |title: &str| FileDialog::new()
+			.set_title(title)
+			.add_filter("xml", &["xml"])
+			.pick_file();
+		let Some(base) = pick("Select Common Base") else { return };
+		let Some(theirs) = pick("Select Version to Merge") else { return };
+		
+		let sides = SchemaData::try_deserialize(base)
+			.and_then(|base| Ok((base, SchemaData::try_deserialize(theirs)?)));
+		match sides {
+			Ok((base, theirs)) => {
+				let merge = SchemaMerge::new(base, doc.file.data.clone(), theirs);
+				self.merge = Some(MergeResolver::open(doc.id, merge));
+			},
+			Err(err) => self.report(format!("Failed to merge: {}", err))
+		}
+	}
+	
+	// Resolves a merge from the command line into the open result.
+	fn resolve_merge(&mut self, [ base, ours, theirs ]: [PathBuf; 3]) {
+		let Some(doc) = self.docs.get(self.active).map(|doc| doc.id) else { return };
+		
+		let sides = SchemaData::try_deserialize(base).and_then(|base| Ok((
+			base,
+			SchemaData::try_deserialize(ours)?,
+			SchemaData::try_deserialize(theirs)?
+		)));
+		match sides {
+			Ok((base, ours, theirs)) => self.merge = Some(MergeResolver::open(doc, SchemaMerge::new(base, ours, theirs))),
+			Err(err) => self.report(format!("Failed to merge: {}", err))
+		}
+	}
+	
+	fn draw_merge(&mut self, ui: &Ui<App>) {
+		let Some(resolver) = self.merge.as_mut() else { return };
+		// Closing the document drops the merge along with it.
+		let Some(doc) = self.docs.iter_mut().find(|doc| doc.id == resolver.doc) else {
+			self.merge = None;
+			return;
+		};
+		
+		let mut open = true;
+		if let Some(data) = resolver.draw(ui, &mut open) {
+			doc.file.data = data;
+			println!("Applied merge.");
+		}
+		if !open {
+			self.merge = None;
+		}
+	}
+	
+	// Image of the open tab in the current view, or a blank canvas.
+	fn preview_image(&self) -> PreviewImage {
+		let file = self.doc().and_then(|doc| {
+			doc.state.view.as_ref()
+				.and_then(|name| doc.file.data.views.iter().find(|view| view.name == *name))
+				.zip(doc.state.active_tab.as_ref())
+				.filter(|(view, file)| view.images.iter().any(|x| x.file == **file))
+				.map(|(_, file)| file.as_str())
+		});
+		self.image_preview(file)
+	}
+	
+	fn image_preview(&self, file: Option<&str>) -> PreviewImage {
+		let blank = PreviewImage { texture: None, dimensions: self.cfg.blank_aspect };
+		let path = file
+			.and_then(|file| Self::image_file(&self.cfg, file))
+			.filter(|path| path.exists());
+		
+		match path {
+			Some(path) => {
+				let mut images = self.images.borrow_mut();
+				let data = images.load(path);
+				PreviewImage {
+					texture: data.texture_id(),
+					dimensions: data.dimensions.unwrap_or(self.cfg.blank_aspect)
+				}
+			},
+			None => blank
+		}
+	}
+	
+	pub fn can_paste(&self) -> bool {
+		self.has_view() && !self.clipboard.is_empty()
+	}
+	
+	// Copies the selected bone, or every bone in the view when none is selected.
+	fn copy_bones(&mut self) {
+		let Some(doc) = self.docs.get_mut(self.active) else { return };
+		let bone = doc.state.bone.clone();
+		let Some(view) = doc.current_view() else { return };
+		
+		self.clipboard = match bone {
+			Some(label) => view.bones.iter().filter(|x| x.label == label).take(1).cloned().collect(),
+			None => view.bones.clone()
+		};
+		println!("Copied {} bone(s).", self.clipboard.len());
+	}
+	
+	// Bones already in the view, matched by name, take the copied position.
+	// Anything else is added as a new bone.
+	fn paste_bones(&mut self) {
+		let Some(doc) = self.docs.get_mut(self.active) else { return };
+		let Some(view) = doc.current_view() else { return };
+		
+		let mut last = None;
+		for bone in &self.clipboard {
+			match view.bones.iter_mut().find(|x| x.name == bone.name) {
+				Some(existing) => {
+					existing.x = bone.x;
+					existing.y = bone.y;
+					last = Some(existing.label.clone());
+				},
+				None => {
+					let label = view.unique_label(&bone.label);
+					last = Some(label.clone());
+					view.bones.push(SchemaBone { label, ..bone.clone() });
+				}
+			}
+		}
+		
+		if last.is_some() {
+			doc.state.bone = last;
+		}
+	}
+	
+	// Data
+	
+	fn doc(&self) -> Option<&Document> {
+		self.docs.get(self.active)
+	}
+	
+	pub fn is_file_loaded(&self) -> bool {
+		!self.docs.is_empty()
+	}
+	
+	pub fn title(&self) -> String {
+		match self.doc() {
+			Some(doc) => {
+				let marker = if doc.file.is_dirty() { "*" } else { "" };
+				format!("{}{} - {}", marker, doc.file.display_name(), TITLE)
+			},
+			None => TITLE.to_string()
+		}
+	}
+	
+	// Documents with unsaved changes that `action` would discard.
+	fn unsaved(&self, action: &FileAction) -> Vec<usize> {
+		let affected: Vec<usize> = match action {
+			FileAction::Close(i) => vec![ *i ],
+			FileAction::Quit => (0..self.docs.len()).collect(),
+			_ => Vec::new()
+		};
+		affected.into_iter()
+			.filter(|i| self.docs.get(*i).is_some_and(|doc| doc.file.is_dirty()))
+			.collect()
+	}
+	
+	pub fn request(&mut self, action: FileAction) {
+		if self.unsaved(&action).is_empty() {
+			self.perform(action);
+		} else {
+			self.pending = Some(action);
+			self.prompt_opening = true;
+		}
+	}
+	
+	pub fn should_quit(&self) -> bool {
+		self.quit
+	}
+	
+	fn perform(&mut self, action: FileAction) {
+		match action {
+			FileAction::New => self.new_file(),
+			FileAction::Open => self.open_file(),
+			FileAction::OpenPath(path) => self.open_path(path),
+			FileAction::Close(i) => self.close_doc(i),
+			FileAction::Quit => self.quit = true
+		}
+	}
+	
+	pub fn draw_prompts(&mut self, ui: &Ui<App>) {
+		self.draw_translations(ui);
+		self.draw_bone_import(ui);
+		self.draw_pose_import(ui);
+		self.draw_csv_import(ui);
+		self.draw_view_import(ui);
+		self.draw_svg_import(ui);
+		self.draw_csv_export(ui);
+		self.draw_preview_export(ui);
+		self.draw_svg_export(ui);
+		self.draw_diff(ui);
+		self.draw_merge(ui);
+		self.draw_errors(ui);
+		
+		const POPUP_NAME: &str = "Unsaved changes";
+		
+		if self.prompt_opening {
+			ui.open_popup(POPUP_NAME);
+			self.prompt_opening = false;
+		}
+		
+		ui.popup_modal_config(POPUP_NAME)
+			.flags(WindowFlags::AlwaysAutoResize)
+			.with(|| {
+				let unsaved = self.pending.as_ref().map(|action| self.unsaved(action)).unwrap_or_default();
+				let names: Vec<String> = unsaved.iter().map(|i| self.docs[*i].file.display_name()).collect();
+				ui.text(&format!("Save changes to {} before continuing?", names.join(", ")));
+				ui.spacing();
+				
+				if ui.button("Save") {
+					ui.close_current_popup();
+					// Cancelling any save dialog cancels the action too.
+					if unsaved.iter().all(|i| self.save_doc(*i, false)) {
+						if let Some(action) = self.pending.take() {
+							self.perform(action);
+						}
+					}
+					self.pending = None;
+				}
+				ui.same_line();
+				if ui.button("Discard") {
+					ui.close_current_popup();
+					if let Some(action) = self.pending.take() {
+						self.perform(action);
+					}
+				}
+				ui.same_line();
+				if dialog::cancel_button(ui, "Cancel") {
+					self.pending = None;
+				}
+			});
+	}
+	
+	// Errors
+	
+	// Logs `message` and shows it in the error dialog, along with any not dismissed yet.
+	fn report(&mut self, message: String) {
+		println!("{}", message);
+		if self.errors.is_empty() {
+			self.errors_opening = true;
+		}
+		self.errors.push(message);
+	}
+	
+	fn draw_errors(&mut self, ui: &Ui<App>) {
+		if self.errors.is_empty() { return }
+		
+		let errors = &self.errors;
+		let open = dialog::modal(ui, "Error", std::mem::take(&mut self.errors_opening), || {
+			for error in errors {
+				ui.text(error);
+			}
+			ui.spacing();
+			dialog::cancel_button(ui, "OK");
+		});
+		if !open {
+			self.errors.clear();
+		}
+	}
+	
+	pub fn open_file(&mut self) {
+		let path = FileDialog::new()
+			.add_filter("xml", &["xml"])
+			.set_directory("/")
+			.pick_file();
+		
+		if let Some(path) = path {
+			self.open_path(path);
+		}
+	}
+	
+	pub fn open_path(&mut self, path: PathBuf) {
+		if !path.exists() {
+			println!("File does not exist: {}", path.display());
+			return;
+		}
+		
+		self.cfg.add_recent_file(&path);
+		
+		// Opening a schema that's already open switches to its tab.
+		if let Some(i) = self.docs.iter().position(|doc| doc.file.path.as_ref() == Some(&path)) {
+			self.select_doc(i);
+			return;
+		}
+		
+		match SchemaFile::open(path) {
+			Ok(file) => self.add_doc(file),
+			Err(err) => self.report(format!("Failed to open schema: {}", err))
+		}
+	}
+	
+	pub fn save_file(&mut self, new_path: bool) -> bool {
+		self.save_doc(self.active, new_path)
+	}
+	
+	fn save_doc(&mut self, index: usize, new_path: bool) -> bool {
+		let Some(doc) = self.docs.get_mut(index) else { return false };
+		let file = &mut doc.file;
+
+		println!("Saving...");
+
+		let path = match &file.path {
+			Some(path) if !new_path => Some(path.clone()),
+			_ => FileDialog::new()
+				.add_filter("xml", &["xml"])
+				.save_file()
+		};
+
+		let Some(path) = path else {
+			println!("Save cancelled");
+			return false;
+		};
+		
+		if let Err(err) = file.write(&path) {
+			self.report(format!("Failed to save: {}", err));
+			return false;
+		}
+		file.path = Some(path.clone());
+		self.cfg.add_recent_file(&path);
+		true
+	}
+
+	pub fn new_file(&mut self) {
+		println!("Creating new schema.");
+		self.add_doc(SchemaFile::default());
+	}
+	
+	fn add_doc(&mut self, file: SchemaFile) {
+		let doc = Document::new(self.next_id, file, &self.cfg);
+		self.next_id += 1;
+		self.docs.push(doc);
+		self.select_doc(self.docs.len() - 1);
+	}
+	
+	fn select_doc(&mut self, index: usize) {
+		self.active = index;
+		self.open_doc = Some(index);
+	}
+	
+	fn close_doc(&mut self, index: usize) {
+		if index >= self.docs.len() { return }
+		
+		self.docs.remove(index);
+		if self.active > index || self.active >= self.docs.len() {
+			self.active = self.active.saturating_sub(1);
+		}
+		if !self.docs.is_empty() {
+			self.select_doc(self.active);
+		}
+	}
+	
+	pub fn save_config(&mut self) {
+		if let Some(doc) = self.doc() {
+			self.cfg.ui.compare_opacity = doc.state.compare.opacity;
+		}
+		self.store_session();
+		self.cfg.save();
+	}
+	
+	pub fn set_image_budget(&mut self, budget: usize) {
+		self.cfg.image_budget = budget;
+		self.images.borrow_mut().set_budget(self.cfg.image_budget_bytes());
+	}
+	
+	pub fn image_memory_usage(&self) -> usize {
+		self.images.borrow().memory_usage()
+	}
+	
+	// Session
+	
+	fn restore_session(&mut self) {
+		let session = std::mem::take(&mut self.cfg.session);
+		// Reopening isn't a use of the files, so Recent Files keeps its order.
+		let recent = self.cfg.recent_files.clone();
+		self.restore_documents(session);
+		self.cfg.recent_files = recent;
+	}
+	
+	fn restore_documents(&mut self, session: Session) {
+		// Reopen the other documents in their tab order first.
+		for path in session.documents {
+			if path.exists() {
+				self.open_path(path);
+			}
+		}
+		
+		let Some(path) = session.schema else { return };
+		
+		println!("Restoring session: {}", path.display());
+		
+		self.open_path(path.clone());
+		let Some(doc) = self.docs.iter_mut().find(|doc| doc.file.path.as_ref() == Some(&path)) else { return };
+		
+		let state = &mut doc.state;
+		state.view = session.view;
+		state.bone = session.bone;
+		state.open_tab = session.tab;
+		state.canvas = session.canvas.into_iter()
+			.map(|canvas| (
+				(canvas.view, canvas.tab),
+				CanvasView { zoom: canvas.zoom, pan: canvas.pan }
+			))
+			.collect();
+	}
+	
+	fn store_session(&mut self) {
+		// Unsaved schemas have no path to reopen, so they're left out.
+		let documents = self.docs.iter()
+			.filter_map(|doc| doc.file.path.clone())
+			.collect();
+		
+		let Some((doc, path)) = self.doc().and_then(|doc| Some((doc, doc.file.path.clone()?))) else {
+			self.cfg.session = Session { documents, ..Session::default() };
+			return;
+		};
+		
+		let state = &doc.state;
+		let canvas = state.canvas.iter()
+			.filter(|(_, transform)| **transform != CanvasView::default())
+			.map(|((view, tab), transform)| CanvasSession {
+				view: view.clone(),
+				tab: tab.clone(),
+				zoom: transform.zoom,
+				pan: transform.pan
+			})
+			.collect();
+		
+		self.cfg.session = Session {
+			schema: Some(path),
+			view: state.view.clone(),
+			bone: state.bone.clone(),
+			tab: state.active_tab.clone(),
+			canvas,
+			documents
+		};
+	}
+}
+
+fn cycle_index(len: usize, current: Option<usize>, offset: isize) -> Option<usize> {
+	if len == 0 { return None }
+	match current {
+		Some(i) => Some((i as isize + offset).rem_euclid(len as isize) as usize),
+		None => Some(0)
+	}
+}
+
+// Document
+
+// An open schema with its own view state and undo history.
+struct Document {
+	id: usize,
+	file: SchemaFile,
+	state: UiState,
+	history: History
+}
+
+impl Document {
+	fn new(id: usize, file: SchemaFile, cfg: &Configuration) -> Self {
+		let mut state = UiState::default();
+		state.compare.opacity = cfg.ui.compare_opacity;
+		
+		let mut history = History::default();
+		history.reset(&file);
+		
+		Self { id, file, state, history }
+	}
+	
+	fn run_action(&mut self, action: Action) {
+		match action {
+			Action::Undo => self.history.undo(&mut self.file),
+			Action::Redo => self.history.redo(&mut self.file),
+			Action::AddBone => self.add_bone(),
+			Action::DeleteBone => self.delete_bone(),
+			Action::NextView => self.cycle_view(1),
+			Action::PrevView => self.cycle_view(-1),
+			Action::NextTab => self.cycle_tab(1),
+			Action::PrevTab => self.cycle_tab(-1),
+			Action::ZoomIn => self.zoom(Some(KEY_ZOOM_STEPS)),
+			Action::ZoomOut => self.zoom(Some(-KEY_ZOOM_STEPS)),
+			Action::ZoomReset => self.zoom(None),
+			Action::NudgeLeft => self.nudge_bone(-NUDGE_STEP, 0.0),
+			Action::NudgeRight => self.nudge_bone(NUDGE_STEP, 0.0),
+			Action::NudgeUp => self.nudge_bone(0.0, -NUDGE_STEP),
+			Action::NudgeDown => self.nudge_bone(0.0, NUDGE_STEP),
+			_ => ()
+		}
+	}
+	
+	fn current_view(&mut self) -> Option<&mut SchemaView> {
+		let name = self.state.view.clone()?;
+		self.file.data.get_view(name)
+	}
+	
+	fn add_bone(&mut self) {
+		let Some(view) = self.current_view() else { return };
+		let label = view.add_bone().label.clone();
+		self.state.bone = Some(label);
+	}
+	
+	fn delete_bone(&mut self) {
+		let Some(label) = self.state.bone.clone() else { return };
+		let Some(view) = self.current_view() else { return };
+		if let Some(i) = view.bones.iter().position(|x| x.label == label) {
+			view.bones.remove(i);
+			self.state.bone = None;
+		}
+	}
+	
+	fn nudge_bone(&mut self, x: f32, y: f32) {
+		let Some(label) = self.state.bone.clone() else { return };
+		let Some(view) = self.current_view() else { return };
+		if let Some(bone) = view.bones.iter_mut().find(|x| x.label == label) {
+			bone.x = (bone.x + x).clamp(0.0, 1.0);
+			bone.y = (bone.y + y).clamp(0.0, 1.0);
+		}
+	}
+	
+	fn cycle_view(&mut self, offset: isize) {
+		let views = &self.file.data.views;
+		
+		let current = self.state.view.as_ref()
+			.and_then(|name| views.iter().position(|x| x.name == *name));
+		if let Some(i) = cycle_index(views.len(), current, offset) {
+			self.state.open_view(&views[i]);
+		}
+	}
+	
+	fn cycle_tab(&mut self, offset: isize) {
+		let active = self.state.active_tab.clone();
+		let Some(view) = self.current_view() else { return };
+		
+		let current = active.and_then(|file| view.images.iter().position(|x| x.file == file));
+		if let Some(i) = cycle_index(view.images.len(), current, offset) {
+			let file = view.images[i].file.clone();
+			self.state.open_tab = Some(file);
+		}
+	}
+	
+	// Zooms the visible canvas around its centre, or resets it with `None`.
+	fn zoom(&mut self, steps: Option<f32>) {
+		let active = self.state.active_tab.clone();
+		let Some(view) = self.current_view() else { return };
+		
+		// Canvases are keyed by tab, views without images use an empty one.
+		let tab = match view.images.is_empty() {
+			true => String::new(),
+			false => match active.filter(|file| view.images.iter().any(|x| x.file == *file)) {
+				Some(file) => file,
+				None => return
+			}
+		};
+		let key = (view.name.clone(), tab);
+		
+		let transform = self.state.canvas.entry(key).or_default();
+		match steps {
+			Some(steps) => transform.zoom_by(steps),
+			None => transform.reset()
+		}
+	}
+}
+
+// State
+
 #[derive(Default)]
 struct UiState {
 	pub view: Option<String>,
 	pub bone: Option<String>,
 	pub open_tab: Option<String>,
+	pub active_tab: Option<String>,
+	pub rename_view: String,
 	pub rename_img: String,
 	pub img_cursor: [ f32; 2 ],
-	pub img_size: [ f32; 2 ]
+	pub img_size: [ f32; 2 ],
+	pub canvas: HashMap<(String, String), CanvasView>,
+	pub compare: CompareState
+}
+
+#[derive(Default, Clone, Copy, PartialEq)]
+enum CompareMode {
+	#[default]
+	Overlay,
+	Split
+}
+
+struct CompareState {
+	pub file: Option<String>,
+	pub mode: CompareMode,
+	pub opacity: f32
+}
+
+impl Default for CompareState {
+	fn default() -> Self {
+		Self {
+			file: None,
+			mode: CompareMode::default(),
+			opacity: 0.5
+		}
+	}
+}
+
+// Canvas
+
+struct CanvasImage {
+	path: Option<PathBuf>,
+	layers: Vec<(PathBuf, f32)>,
+	opacity: f32
+}
+
+impl CanvasImage {
+	fn blank() -> Self {
+		Self {
+			path: None,
+			layers: Vec::new(),
+			opacity: 1.0
+		}
+	}
+	
+	fn new(cfg: &Configuration, file: &str, layers: &[ImageLayer], opacity: f32) -> Self {
+		let layers = layers.iter()
+			.filter(|layer| layer.visible)
+			.filter_map(|layer| Some((Editor::image_file(cfg, &layer.file)?, layer.opacity)))
+			.collect();
+		Self {
+			path: Editor::image_file(cfg, file),
+			layers,
+			opacity
+		}
+	}
+}
+
+struct CanvasPane<'a> {
+	id: &'a str,
+	size: Vector2,
+	dimensions: [u32; 2],
+	is_blank: bool,
+	notice: Option<&'a str>,
+	marker: MarkerStyle,
+	diff: Option<&'a ViewDiff>,
+	stack: &'a [CanvasImage]
+}
+
+struct CanvasTab<'a> {
+	key: (String, String),
+	base: CanvasImage,
+	compare: Option<CanvasImage>,
+	blank: [u32; 2],
+	notice: Option<String>,
+	marker: MarkerStyle,
+	diff: Option<&'a ViewDiff>
+}
+
+#[derive(Clone, Copy)]
+struct MarkerStyle {
+	radius: f32,
+	color: Color,
+	selected: Color
+}
+
+impl MarkerStyle {
+	fn new(cfg: &Configuration) -> Self {
+		Self {
+			radius: cfg.ui.marker_radius,
+			color: theme::color(cfg.ui.marker_color),
+			selected: theme::color(cfg.ui.marker_selected_color)
+		}
+	}
 }
 
 impl UiState {
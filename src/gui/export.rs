@@ -0,0 +1,210 @@
+﻿use crate::{
+	app::App,
+	gui::dialog,
+	schema::{
+		csv,
+		data::SchemaData,
+		skeleton::{self, SkeletonBone}
+	}
+};
+use std::path::PathBuf;
+use easy_imgui::{Color, Ui};
+use rfd::FileDialog;
+
+// CSV export
+
+pub struct CsvExport {
+	all_views: bool,
+	pixels: bool,
+	resolution: [i32; 2],
+	opening: bool
+}
+
+impl CsvExport {
+	pub fn open(resolution: [u32; 2]) -> Self {
+		Self {
+			all_views: false,
+			pixels: false,
+			resolution: resolution.map(|x| x as i32),
+			opening: true
+		}
+	}
+	
+	// Draws the options. Returns the CSV content once confirmed.
+	pub fn draw(&mut self, ui: &Ui<App>, data: &SchemaData, view: Option<&str>, open: &mut bool) -> Option<String> {
+		const POPUP_NAME: &str = "Export CSV";
+		
+		let mut result = None;
+		*open = dialog::modal(ui, POPUP_NAME, std::mem::take(&mut self.opening), || {
+			// Without an open view there's only the whole schema to export.
+			let all_views = self.all_views || view.is_none();
+			if let Some(view) = view {
+				if ui.radio_button_config(format!("View \"{}\"", view), !all_views).build() {
+					self.all_views = false;
+				}
+			}
+			if ui.radio_button_config("All views", all_views).build() {
+				self.all_views = true;
+			}
+			
+			ui.spacing();
+			
+			ui.checkbox("Include pixel coordinates", &mut self.pixels);
+			ui.with_disabled(!self.pixels, || {
+				ui.set_next_item_width(200.0);
+				if ui.input_int_2_config("Resolution", &mut self.resolution).build() {
+					self.resolution = self.resolution.map(|x| x.max(1));
+				}
+			});
+			
+			let views: Vec<_> = data.views.iter()
+				.filter(|x| all_views || Some(x.name.as_str()) == view)
+				.collect();
+			let bones: usize = views.iter().map(|x| x.bones.len()).sum();
+			ui.text_disabled(format!("{} bone(s) in {} view(s)", bones, views.len()));
+			ui.spacing();
+			
+			if ui.button("Export...") {
+				ui.close_current_popup();
+				let pixels = self.pixels.then(|| self.resolution.map(|x| x as u32));
+				result = Some(csv::export(&views, pixels));
+			}
+			ui.same_line();
+			dialog::cancel_button(ui, "Cancel");
+		});
+		
+		result
+	}
+}
+
+// Preview export
+
+#[derive(Default)]
+pub struct PreviewOptions {
+	pub labels: bool,
+	pub hit_map: bool,
+	pub hierarchy: Option<Vec<SkeletonBone>>
+}
+
+pub struct PreviewExport {
+	options: PreviewOptions,
+	// Bone list the hierarchy was read from, or the error reading it.
+	bone_list: Option<(PathBuf, Result<(), String>)>,
+	opening: bool
+}
+
+impl PreviewExport {
+	pub fn open() -> Self {
+		Self {
+			options: PreviewOptions { labels: true, ..PreviewOptions::default() },
+			bone_list: None,
+			opening: true
+		}
+	}
+	
+	// Draws the options. Returns them once confirmed.
+	pub fn draw(&mut self, ui: &Ui<App>, view: &str, open: &mut bool) -> Option<PreviewOptions> {
+		const POPUP_NAME: &str = "Export Preview";
+		
+		let mut result = None;
+		*open = dialog::modal(ui, POPUP_NAME, std::mem::take(&mut self.opening), || {
+			ui.text(&format!("View \"{}\" as PNG", view));
+			ui.spacing();
+			
+			ui.checkbox("Bone labels", &mut self.options.labels);
+			ui.checkbox("Hit map", &mut self.options.hit_map);
+			
+			// Hierarchy
+			
+			ui.text("Hierarchy lines:");
+			ui.same_line();
+			match &self.bone_list {
+				Some((path, Ok(()))) => ui.text(&path.file_name().map(|x| x.to_string_lossy()).unwrap_or_default()),
+				Some((_, Err(err))) => ui.text_colored(Color::new(1.0, 0.4, 0.4, 1.0), err),
+				None => ui.text_disabled("None")
+			}
+			ui.same_line();
+			if ui.button("Bone List...") {
+				self.browse_bone_list();
+			}
+			if self.bone_list.is_some() {
+				ui.same_line();
+				if ui.button("Clear") {
+					self.bone_list = None;
+					self.options.hierarchy = None;
+				}
+			}
+			ui.spacing();
+			
+			if ui.button("Export...") {
+				ui.close_current_popup();
+				result = Some(std::mem::take(&mut self.options));
+			}
+			ui.same_line();
+			dialog::cancel_button(ui, "Cancel");
+		});
+		
+		result
+	}
+	
+	fn browse_bone_list(&mut self) {
+		let path = FileDialog::new()
+			.add_filter("Bone lists", &["txt", "json", "xml"])
+			.add_filter("All files", &["*"])
+			.pick_file();
+		let Some(path) = path else { return };
+		
+		match skeleton::read_bone_list(&path) {
+			Ok(bones) => {
+				self.options.hierarchy = Some(bones);
+				self.bone_list = Some((path, Ok(())));
+			},
+			Err(err) => {
+				self.options.hierarchy = None;
+				self.bone_list = Some((path, Err(err)));
+			}
+		}
+	}
+}
+
+// SVG export
+
+pub struct SvgExport {
+	// Embedded images keep the file self-contained, linked ones keep it small.
+	embed: bool,
+	opening: bool
+}
+
+impl SvgExport {
+	pub fn open() -> Self {
+		Self { embed: true, opening: true }
+	}
+	
+	// Draws the options. Returns whether to embed the image once confirmed.
+	pub fn draw(&mut self, ui: &Ui<App>, view: &str, open: &mut bool) -> Option<bool> {
+		const POPUP_NAME: &str = "Export SVG";
+		
+		let mut result = None;
+		*open = dialog::modal(ui, POPUP_NAME, std::mem::take(&mut self.opening), || {
+			ui.text(&format!("View \"{}\" as SVG", view));
+			ui.spacing();
+			
+			if ui.radio_button_config("Embed image", self.embed).build() {
+				self.embed = true;
+			}
+			if ui.radio_button_config("Link to image file", !self.embed).build() {
+				self.embed = false;
+			}
+			ui.spacing();
+			
+			if ui.button("Export...") {
+				ui.close_current_popup();
+				result = Some(self.embed);
+			}
+			ui.same_line();
+			dialog::cancel_button(ui, "Cancel");
+		});
+		
+		result
+	}
+}
\ No newline at end of file
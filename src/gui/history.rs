@@ -0,0 +1,85 @@
+﻿use crate::schema::{SchemaFile, data::SchemaData, project::SchemaProject};
+
+const MAX_HISTORY: usize = 100;
+
+// History
+
+// Undo works on whole snapshots of the schema and its project, which are small
+// enough that diffing individual edits isn't worth the complexity.
+
+#[derive(Clone, Default)]
+struct Snapshot {
+	data: SchemaData,
+	project: SchemaProject
+}
+
+impl Snapshot {
+	fn of(file: &SchemaFile) -> Self {
+		Self {
+			data: file.data.clone(),
+			project: file.project.clone()
+		}
+	}
+	
+	fn matches(&self, file: &SchemaFile) -> bool {
+		self.data == file.data && self.project == file.project
+	}
+	
+	fn apply(self, file: &mut SchemaFile) {
+		file.data = self.data;
+		file.project = self.project;
+	}
+}
+
+#[derive(Default)]
+pub struct History {
+	undo: Vec<Snapshot>,
+	redo: Vec<Snapshot>,
+	current: Snapshot
+}
+
+impl History {
+	pub fn reset(&mut self, file: &SchemaFile) {
+		self.undo.clear();
+		self.redo.clear();
+		self.current = Snapshot::of(file);
+	}
+	
+	// Records a step when the file differs from the last snapshot. While `settled`
+	// is false (e.g. a drag in progress) changes are held back, so one drag is one step.
+	pub fn track(&mut self, file: &SchemaFile, settled: bool) {
+		if !settled || self.current.matches(file) { return }
+		
+		let previous = std::mem::replace(&mut self.current, Snapshot::of(file));
+		self.undo.push(previous);
+		if self.undo.len() > MAX_HISTORY {
+			self.undo.remove(0);
+		}
+		self.redo.clear();
+	}
+	
+	pub fn can_undo(&self) -> bool {
+		!self.undo.is_empty()
+	}
+	
+	pub fn can_redo(&self) -> bool {
+		!self.redo.is_empty()
+	}
+	
+	pub fn undo(&mut self, file: &mut SchemaFile) {
+		// Changes not yet recorded are undone first.
+		self.track(file, true);
+		
+		let Some(snapshot) = self.undo.pop() else { return };
+		let current = std::mem::replace(&mut self.current, snapshot.clone());
+		self.redo.push(current);
+		snapshot.apply(file);
+	}
+	
+	pub fn redo(&mut self, file: &mut SchemaFile) {
+		let Some(snapshot) = self.redo.pop() else { return };
+		let current = std::mem::replace(&mut self.current, snapshot.clone());
+		self.undo.push(current);
+		snapshot.apply(file);
+	}
+}
\ No newline at end of file
@@ -1,95 +1,347 @@
-﻿use crate::app::App;
-use std::{
-	fs,
-	collections::HashMap,
-	path::PathBuf
+﻿use std::{
+	fs::{self, File},
+	collections::{HashMap, HashSet},
+	io::BufReader,
+	path::{Path, PathBuf},
+	sync::mpsc::{self, Receiver, Sender},
+	thread,
+	time::{Duration, Instant, SystemTime}
 };
 use easy_imgui::{
-	CustomRectIndex, Ui, FontAtlasMut,
-	image::{load_from_memory_with_format, ImageFormat, DynamicImage, GenericImage}
+	TextureId,
+	image::{codecs::png::PngDecoder, imageops::FilterType, DynamicImage, ImageDecoder}
 };
+use easy_imgui_renderer::{
+	Renderer,
+	glow::{self, HasContext},
+	glr::{GlContext, Texture}
+};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+const RELOAD_NOTICE: Duration = Duration::from_secs(3);
 
 // ImageLoader
 
 pub struct ImageLoader {
-	images: HashMap<PathBuf, ImageData>
+	gl: GlContext,
+	images: HashMap<PathBuf, ImageData>,
+	budget: usize,
+	frame: u64,
+	last_poll: Instant,
+	// Paths sent to the decoder and not received back yet, evicted entries included.
+	queued: HashSet<PathBuf>,
+	jobs: Sender<PathBuf>,
+	events: Receiver<DecodeEvent>
 }
 
 impl ImageLoader {
-	pub fn new() -> ImageLoader {
+	pub fn new(gl: GlContext) -> ImageLoader {
+		let max_size = unsafe { gl.get_parameter_i32(glow::MAX_TEXTURE_SIZE) };
+		let (jobs, events) = Decoder::spawn(max_size.max(1) as u32);
 		ImageLoader {
-			images: HashMap::new()
+			gl,
+			images: HashMap::new(),
+			budget: usize::MAX,
+			frame: 0,
+			last_poll: Instant::now(),
+			queued: HashSet::new(),
+			jobs,
+			events
 		}
 	}
 	
-	pub fn build_atlas(&mut self, atlas: &mut FontAtlasMut<'_, App>) {
-		println!("Building atlas.");
-		
-		for (path, data) in &mut self.images {
-			if let Some(img) = &data.image {
-				println!("Loading {}", path.display());
-				
-				let img = img.clone();
-				let rect = atlas.add_custom_rect_regular([img.width(), img.height()], move |_, pixels| {
-					pixels.copy_from(&img, 0, 0).unwrap();
-				});
-				data.rect = Some(rect);
-			}
-		}
+	pub fn set_budget(&mut self, budget: usize) {
+		self.budget = budget;
+	}
+	
+	pub fn memory_usage(&self) -> usize {
+		self.images.values().map(|data| data.size).sum()
 	}
 	
 	pub fn update(&mut self) {
-		let mut remove_paths = Vec::<PathBuf>::new();
+		self.frame += 1;
+		
+		self.receive();
+		
+		if self.last_poll.elapsed() >= POLL_INTERVAL {
+			self.last_poll = Instant::now();
+			self.poll_changes();
+		}
+		
+		let mut unused = Vec::<(u64, PathBuf)>::new();
 		
 		for (path, img) in &mut self.images {
 			if img.used {
 				img.used = false;
+				img.last_used = self.frame;
 			} else {
-				//println!("culling {}", path.display());
-				remove_paths.push(path.clone());
+				unused.push((img.last_used, path.clone()));
+			}
+		}
+		
+		// Evict the least recently used images until we're back under budget.
+		// Images drawn this frame are never evicted, even if they alone exceed it.
+		
+		let mut usage = self.memory_usage();
+		if usage <= self.budget { return }
+		
+		unused.sort_by_key(|(last_used, _)| *last_used);
+		
+		for (_, path) in unused {
+			if usage <= self.budget { break }
+			
+			if let Some(data) = self.images.remove(&path) {
+				println!("Evicting {}", path.display());
+				usage -= data.size;
 			}
 		}
 	}
 	
-	pub fn load(&mut self, ui: &Ui<App>, path: PathBuf) -> &ImageData {
-		 if !self.images.contains_key(&path) {
-			let mut img: Option<DynamicImage> = None;
-
-			if path.exists() {
-				if let Ok(bytes) = fs::read(&path) {
-					let result = load_from_memory_with_format(&bytes, ImageFormat::Png);
-					if let Ok(value) = result {
-						img = Some(value);
-					} else if let Err(err) = result {
-						println!("{}", err);
+	pub fn load(&mut self, path: PathBuf) -> &ImageData {
+		if !self.images.contains_key(&path) {
+			let data = ImageData::new(Self::modified_time(&path));
+			self.images.insert(path.clone(), data);
+			self.request(&path);
+		}
+		
+		let data = self.images.get_mut(&path).unwrap();
+		data.used = true;
+		data
+	}
+	
+	pub fn was_reloaded(&self, path: &Path) -> bool {
+		match self.images.get(path).and_then(|data| data.reloaded) {
+			Some(time) => time.elapsed() < RELOAD_NOTICE,
+			None => false
+		}
+	}
+	
+	// Hot reload
+	
+	fn poll_changes(&mut self) {
+		let mut changed = Vec::new();
+		for (path, data) in &mut self.images {
+			if data.pending { continue }
+			
+			let modified = Self::modified_time(path);
+			if modified == data.modified { continue }
+			
+			println!("Reloading {}", path.display());
+			
+			data.modified = modified;
+			changed.push(path.clone());
+		}
+		for path in changed {
+			self.request(&path);
+		}
+	}
+	
+	// Decoding
+	
+	fn request(&mut self, path: &Path) {
+		if let Some(data) = self.images.get_mut(path) {
+			data.pending = true;
+		}
+		// An entry evicted and loaded again before its decode came back waits for that one.
+		if self.queued.insert(path.to_path_buf()) {
+			self.jobs.send(path.to_path_buf()).expect("Image decoder has stopped");
+		}
+	}
+	
+	fn receive(&mut self) {
+		while let Ok(event) = self.events.try_recv() {
+			match event {
+				DecodeEvent::Header(path, dimensions) => {
+					// Entries evicted while decoding are simply dropped.
+					if let Some(data) = self.images.get_mut(&path) {
+						data.dimensions = Some(dimensions);
+					}
+				},
+				DecodeEvent::Decoded(path, result) => {
+					self.queued.remove(&path);
+					if !self.images.contains_key(&path) { continue }
+					
+					let (status, size) = match result {
+						Ok(decoded) => match self.upload(&decoded) {
+							Ok(texture) => (ImageStatus::Ready(texture), decoded.texture_size()),
+							Err(err) => (ImageStatus::Failed(format!("Texture upload failed: {}", err)), 0)
+						},
+						Err(err) => (ImageStatus::Failed(err), 0)
+					};
+					
+					let data = self.images.get_mut(&path).unwrap();
+					match status {
+						// A file caught mid-write fails to decode, the old texture stays until the next change.
+						ImageStatus::Failed(err) if data.is_ready() => {
+							println!("Failed to reload {}: {}", path.display(), err);
+							data.pending = false;
+						},
+						status => {
+							let was_ready = data.is_ready();
+							data.set_status(status, size);
+							if was_ready {
+								data.reloaded = Some(Instant::now());
+							}
+						}
 					}
 				}
 			}
-			
-			self.images.insert(path.clone(), ImageData::new(img));
-			ui.invalidate_font_atlas(); 
 		}
+	}
+	
+	fn upload(&self, decoded: &Decoded) -> easy_imgui_renderer::glr::Result<Texture> {
+		let texture = Texture::generate(&self.gl)?;
+		unsafe {
+			let gl = &self.gl;
+			gl.bind_texture(glow::TEXTURE_2D, Some(texture.id()));
+			gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_S, glow::CLAMP_TO_EDGE as i32);
+			gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_T, glow::CLAMP_TO_EDGE as i32);
+			gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MIN_FILTER, glow::LINEAR as i32);
+			gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MAG_FILTER, glow::LINEAR as i32);
+			gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MAX_LEVEL, 0);
+			gl.tex_image_2d(
+				glow::TEXTURE_2D, 0, glow::RGBA as i32,
+				decoded.width as i32, decoded.height as i32, 0,
+				glow::RGBA, glow::UNSIGNED_BYTE,
+				Some(&decoded.pixels)
+			);
+			gl.bind_texture(glow::TEXTURE_2D, None);
+		}
+		Ok(texture)
+	}
+	
+	fn modified_time(path: &Path) -> Option<SystemTime> {
+		fs::metadata(path).and_then(|meta| meta.modified()).ok()
+	}
+}
+
+// Decoder
+
+enum DecodeEvent {
+	Header(PathBuf, [u32; 2]),
+	Decoded(PathBuf, Result<Decoded, String>)
+}
+
+struct Decoded {
+	width: u32,
+	height: u32,
+	pixels: Vec<u8>
+}
+
+impl Decoded {
+	// Bytes the texture takes on the GPU. Downscaled images count at the size they were
+	// uploaded at, not their original dimensions, so eviction frees only what's needed.
+	fn texture_size(&self) -> usize {
+		self.width as usize * self.height as usize * 4
+	}
+}
+
+struct Decoder {
+	max_size: u32,
+	events: Sender<DecodeEvent>
+}
+
+impl Decoder {
+	fn spawn(max_size: u32) -> (Sender<PathBuf>, Receiver<DecodeEvent>) {
+		let (jobs, job_rx) = mpsc::channel::<PathBuf>();
+		let (events, event_rx) = mpsc::channel();
 		
-		let data = self.images.get_mut(&path).unwrap();
-		data.used = true;
-		return data;
+		let decoder = Decoder { max_size, events };
+		thread::Builder::new()
+			.name("image-decoder".into())
+			.spawn(move || {
+				// Exits once the loader is dropped and the job channel closes.
+				for path in job_rx {
+					let result = decoder.decode(&path);
+					if decoder.events.send(DecodeEvent::Decoded(path, result)).is_err() { break }
+				}
+			})
+			.expect("Failed to spawn image decoder");
+		
+		(jobs, event_rx)
+	}
+	
+	fn decode(&self, path: &Path) -> Result<Decoded, String> {
+		let file = File::open(path).map_err(|err| err.to_string())?;
+		let decoder = PngDecoder::new(BufReader::new(file)).map_err(|err| err.to_string())?;
+		
+		// Report the size early so the editor can lay out a placeholder.
+		let (width, height) = decoder.dimensions();
+		self.events.send(DecodeEvent::Header(path.to_path_buf(), [ width, height ])).ok();
+		
+		let img = DynamicImage::from_decoder(decoder).map_err(|err| err.to_string())?;
+		
+		// Textures larger than the GPU supports are downscaled, the original
+		// dimensions are still reported so the aspect ratio is unaffected.
+		
+		let img = if width > self.max_size || height > self.max_size {
+			img.resize(self.max_size, self.max_size, FilterType::Triangle)
+		} else {
+			img
+		};
+		
+		Ok(Decoded {
+			width: img.width(),
+			height: img.height(),
+			pixels: img.into_rgba8().into_raw()
+		})
 	}
 }
 
 // ImageData
 
+enum ImageStatus {
+	Loading,
+	Ready(Texture),
+	Failed(String)
+}
+
 pub struct ImageData {
 	pub used: bool,
-	pub image: Option<DynamicImage>,
-	pub rect: Option<CustomRectIndex>
+	pub last_used: u64,
+	pub size: usize,
+	pub modified: Option<SystemTime>,
+	pub reloaded: Option<Instant>,
+	pub dimensions: Option<[u32; 2]>,
+	pending: bool,
+	status: ImageStatus
 }
 
 impl ImageData {
-	pub fn new(image: Option<DynamicImage>) -> Self {
+	fn new(modified: Option<SystemTime>) -> Self {
 		Self {
 			used: true,
-			image,
-			rect: None
+			last_used: 0,
+			size: 0,
+			modified,
+			reloaded: None,
+			dimensions: None,
+			pending: false,
+			status: ImageStatus::Loading
+		}
+	}
+	
+	fn set_status(&mut self, status: ImageStatus, size: usize) {
+		self.pending = false;
+		self.size = size;
+		self.status = status;
+	}
+	
+	fn is_ready(&self) -> bool {
+		matches!(self.status, ImageStatus::Ready(_))
+	}
+	
+	pub fn error(&self) -> Option<&str> {
+		match &self.status {
+			ImageStatus::Failed(err) => Some(err),
+			_ => None
+		}
+	}
+	
+	pub fn texture_id(&self) -> Option<TextureId> {
+		match &self.status {
+			ImageStatus::Ready(tex) => Some(Renderer::map_tex(tex.id())),
+			_ => None
 		}
 	}
 }
\ No newline at end of file
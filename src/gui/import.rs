@@ -0,0 +1,813 @@
+﻿use crate::{
+	app::App,
+	gui::{canvas, dialog},
+	render::svg::{SvgLayout, SvgMove},
+	schema::{
+		csv::{self, ChangeKind, Coordinates, CsvChange, CsvTable},
+		data::{SchemaBone, SchemaData, SchemaView},
+		pose::{Axis, Camera, Pose},
+		skeleton::{self, SkeletonBone},
+		SchemaSerializer
+	}
+};
+use std::path::PathBuf;
+use easy_imgui::{ChildFlags, Color, ItemId, TableFlags, TableRowFlags, TextureId, Ui, Vector2};
+
+const INDENT_WIDTH: f32 = 12.0;
+
+// Bone list import
+
+// Checklist of bones read from a skeleton bone list, to be created in the open view.
+pub struct BoneImport {
+	path: PathBuf,
+	bones: Result<Vec<SkeletonBone>, String>,
+	depths: Vec<usize>,
+	checked: Vec<bool>,
+	filter: String,
+	opening: bool
+}
+
+impl BoneImport {
+	pub fn open(path: PathBuf) -> Self {
+		let bones = skeleton::read_bone_list(&path);
+		let (depths, checked) = match &bones {
+			Ok(bones) => (skeleton::depths(bones), vec![ true; bones.len() ]),
+			Err(_) => (Vec::new(), Vec::new())
+		};
+		Self {
+			path,
+			bones,
+			depths,
+			checked,
+			filter: String::new(),
+			opening: true
+		}
+	}
+	
+	// Draws the checklist. Returns the checked names once confirmed, or `None` while still open.
+	pub fn draw(&mut self, ui: &Ui<App>, view: &SchemaView, open: &mut bool) -> Option<Vec<String>> {
+		const POPUP_NAME: &str = "Import bone list";
+		
+		let mut result = None;
+		*open = dialog::modal(ui, POPUP_NAME, std::mem::take(&mut self.opening), || {
+			let file = self.path.file_name().map(|x| x.to_string_lossy()).unwrap_or_default();
+			ui.text(&format!("{} into view \"{}\"", file, view.name));
+			
+			let bones = match &self.bones {
+				Ok(bones) => bones,
+				Err(err) => {
+					ui.text_colored(Color::new(1.0, 0.4, 0.4, 1.0), format!("Failed to read bones: {}", err));
+					dialog::cancel_button(ui, "Close");
+					return;
+				}
+			};
+			
+			let exists = |bone: &SkeletonBone| view.bones.iter().any(|x| x.name == bone.name);
+			let filter = self.filter.to_lowercase();
+			let is_shown = |bone: &SkeletonBone| filter.is_empty() || bone.name.to_lowercase().contains(&filter);
+			
+			ui.set_next_item_width(250.0);
+			ui.input_text_hint_config("##filter", "Filter", &mut self.filter).build();
+			ui.same_line();
+			// Only bones that pass the filter are toggled.
+			let mut set_all = None;
+			if ui.button("All") { set_all = Some(true) }
+			ui.same_line();
+			if ui.button("None") { set_all = Some(false) }
+			if let Some(value) = set_all {
+				for (i, bone) in bones.iter().enumerate() {
+					if is_shown(bone) {
+						self.checked[i] = value;
+					}
+				}
+			}
+			
+			ui.child_config("##bones")
+				.size(Vector2::new(400.0, 400.0))
+				.child_flags(ChildFlags::Border)
+				.with(|| {
+					for (i, bone) in bones.iter().enumerate() {
+						if !is_shown(bone) { continue }
+						
+						// Hierarchy is only shown while unfiltered, it's misleading with gaps.
+						let indent = match filter.is_empty() {
+							true => self.depths[i] as f32 * INDENT_WIDTH,
+							false => 0.0
+						};
+						
+						ui.with_push(ItemId(i), || {
+							if indent > 0.0 { ui.indent(indent) }
+							if exists(bone) {
+								ui.with_disabled(true, || {
+									ui.checkbox(format!("{} (exists)", bone.name), &mut true);
+								});
+							} else {
+								ui.checkbox(&bone.name, &mut self.checked[i]);
+								ui.with_item_tooltip(|| {
+									ui.text(&skeleton::label_from_name(&bone.name));
+								});
+							}
+							if indent > 0.0 { ui.unindent(indent) }
+						});
+					}
+				});
+			
+			let names: Vec<String> = bones.iter().enumerate()
+				.filter(|(i, bone)| self.checked[*i] && !exists(bone))
+				.map(|(_, bone)| bone.name.clone())
+				.collect();
+			ui.text(&format!("{} bone(s) to create", names.len()));
+			ui.spacing();
+			
+			ui.with_disabled(names.is_empty(), || {
+				if ui.button("Create Bones") {
+					ui.close_current_popup();
+					result = Some(names);
+				}
+			});
+			ui.same_line();
+			dialog::cancel_button(ui, "Cancel");
+		});
+		
+		result
+	}
+}
+
+// Pose import
+
+const PREVIEW_WIDTH: f32 = 600.0;
+const PREVIEW_HEIGHT: f32 = 400.0;
+
+// Image of the open tab, drawn behind the pose preview.
+pub struct PreviewImage {
+	pub texture: Option<TextureId>,
+	pub dimensions: [u32; 2]
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum CameraMode {
+	File,
+	Orthographic
+}
+
+// Bones projected from a 3D pose, previewed before they're moved or created in the open view.
+pub struct PoseImport {
+	path: PathBuf,
+	pose: Result<Pose, String>,
+	mode: CameraMode,
+	axis: Axis,
+	margin: f32,
+	checked: Vec<bool>,
+	create_missing: bool,
+	opening: bool
+}
+
+impl PoseImport {
+	pub fn open(path: PathBuf) -> Self {
+		let pose = Pose::read(&path);
+		let checked = pose.as_ref().map_or(Vec::new(), |pose| vec![ true; pose.bones.len() ]);
+		let (mode, axis) = match pose.as_ref().ok().and_then(|pose| pose.camera) {
+			Some(Camera::Orthographic { axis, .. }) => (CameraMode::File, axis),
+			Some(Camera::Matrix(_)) => (CameraMode::File, Axis::default()),
+			None => (CameraMode::Orthographic, Axis::default())
+		};
+		Self {
+			path,
+			pose,
+			mode,
+			axis,
+			margin: 0.1,
+			checked,
+			create_missing: true,
+			opening: true
+		}
+	}
+	
+	// Draws the options and preview. Returns the positions to apply once confirmed.
+	pub fn draw(&mut self, ui: &Ui<App>, view: &SchemaView, image: &PreviewImage, open: &mut bool) -> Option<Vec<(String, [f32; 2])>> {
+		const POPUP_NAME: &str = "Import pose";
+		
+		let mut result = None;
+		*open = dialog::modal(ui, POPUP_NAME, std::mem::take(&mut self.opening), || {
+			let file = self.path.file_name().map(|x| x.to_string_lossy()).unwrap_or_default();
+			ui.text(&format!("{} into view \"{}\"", file, view.name));
+			
+			let pose = match &self.pose {
+				Ok(pose) => pose,
+				Err(err) => {
+					ui.text_colored(Color::new(1.0, 0.4, 0.4, 1.0), format!("Failed to read pose: {}", err));
+					dialog::cancel_button(ui, "Close");
+					return;
+				}
+			};
+			
+			// Camera
+			
+			let file_camera = pose.camera;
+			let mode_label = |mode: CameraMode| match mode {
+				CameraMode::File => "From file",
+				CameraMode::Orthographic => "Orthographic"
+			};
+			ui.set_next_item_width(200.0);
+			ui.combo_config("Camera").preview_value(mode_label(self.mode)).with(|| {
+				for mode in [ CameraMode::File, CameraMode::Orthographic ] {
+					if mode == CameraMode::File && file_camera.is_none() { continue }
+					if ui.selectable_config(mode_label(mode)).selected(self.mode == mode).build() {
+						self.mode = mode;
+					}
+				}
+			});
+			
+			let camera = match (self.mode, file_camera) {
+				(CameraMode::File, Some(camera)) => camera,
+				_ => {
+					ui.set_next_item_width(200.0);
+					ui.combo_config("Looking along").preview_value(self.axis.label()).with(|| {
+						for axis in Axis::ALL {
+							if ui.selectable_config(axis.label()).selected(self.axis == axis).build() {
+								self.axis = axis;
+							}
+						}
+					});
+					Camera::Orthographic { axis: self.axis, bounds: None }
+				}
+			};
+			
+			// Margin only applies when fitting the pose to the image.
+			let fits = matches!(camera, Camera::Orthographic { bounds: None, .. });
+			ui.with_disabled(!fits, || {
+				ui.set_next_item_width(200.0);
+				ui.slider_float_config("Margin", &mut self.margin).range(0.0, 0.45).build();
+			});
+			ui.checkbox("Create missing bones", &mut self.create_missing);
+			
+			let [ width, height ] = image.dimensions;
+			let aspect = width.max(1) as f32 / height.max(1) as f32;
+			let projected = pose.project(camera, aspect, self.margin);
+			
+			ui.spacing();
+			
+			// Bones
+			
+			ui.with_group(|| {
+				let flags = TableFlags::RowBg | TableFlags::Borders | TableFlags::SizingFixedFit | TableFlags::ScrollY;
+				ui.table_config("##pose", 3)
+					.flags(flags)
+					.outer_size(Vector2::new(420.0, PREVIEW_HEIGHT))
+					.with(|| {
+						for (i, (bone, pos)) in pose.bones.iter().zip(&projected).enumerate() {
+							let exists = view.bones.iter().any(|x| x.name == bone.name);
+							let status = match pos {
+								None => "Behind camera",
+								Some(_) if !exists && !self.create_missing => "Skipped",
+								Some(pos) if !is_inside(*pos) => "Outside image",
+								Some(_) if exists => "Update",
+								Some(_) => "New"
+							};
+							let usable = pos.is_some() && (exists || self.create_missing);
+							
+							ui.table_next_row(TableRowFlags::None, 0.0);
+							ui.table_next_column();
+							ui.with_push(ItemId(i), || {
+								ui.with_disabled(!usable, || {
+									ui.checkbox(&bone.name, &mut self.checked[i]);
+								});
+							});
+							
+							ui.table_next_column();
+							match usable {
+								true => ui.text(status),
+								false => ui.text_disabled(status)
+							}
+							
+							ui.table_next_column();
+							if let Some([ x, y ]) = pos {
+								ui.text(&format!("{:.3}, {:.3}", x, y));
+							}
+						}
+					});
+			});
+			
+			let positions: Vec<(String, [f32; 2])> = pose.bones.iter().zip(&projected).enumerate()
+				.filter(|(i, (bone, _))| self.checked[*i] && (self.create_missing || view.bones.iter().any(|x| x.name == bone.name)))
+				.filter_map(|(_, (bone, pos))| Some((bone.name.clone(), (*pos)?)))
+				.collect();
+			
+			// Preview
+			
+			ui.same_line();
+			let height = PREVIEW_HEIGHT.min(PREVIEW_WIDTH / aspect);
+			let size = Vector2::new(height * aspect, height);
+			let pos = ui.get_cursor_screen_pos();
+			ui.dummy(size);
+			draw_pose_preview(ui, pos, size, image, view, &positions);
+			
+			let outside = positions.iter().filter(|(_, pos)| !is_inside(*pos)).count();
+			ui.text(&format!("{} bone(s) to apply", positions.len()));
+			if outside > 0 {
+				ui.same_line();
+				ui.text_colored(Color::new(1.0, 0.7, 0.4, 1.0), format!("{} outside the image will be clamped to its edge", outside));
+			}
+			ui.spacing();
+			
+			ui.with_disabled(positions.is_empty(), || {
+				if ui.button("Apply") {
+					ui.close_current_popup();
+					result = Some(positions);
+				}
+			});
+			ui.same_line();
+			dialog::cancel_button(ui, "Cancel");
+		});
+		
+		result
+	}
+}
+
+fn is_inside([ x, y ]: [f32; 2]) -> bool {
+	(0.0..=1.0).contains(&x) && (0.0..=1.0).contains(&y)
+}
+
+// Current positions are drawn hollow with a line to where they'll move, new bones filled.
+fn draw_pose_preview(ui: &Ui<App>, pos: Vector2, size: Vector2, image: &PreviewImage, view: &SchemaView, positions: &[(String, [f32; 2])]) {
+	const RADIUS: f32 = 4.0;
+	let moved = Color::new(1.0, 0.85, 0.3, 1.0);
+	let added = Color::new(0.4, 1.0, 0.5, 1.0);
+	let old = Color::new(0.7, 0.7, 0.7, 0.8);
+	
+	let draw = ui.window_draw_list();
+	match image.texture {
+		Some(tex) => draw.add_image(tex, pos, pos + size, Vector2::new(0.0, 0.0), Vector2::new(1.0, 1.0), Color::WHITE),
+		None => canvas::draw_checkerboard(ui, pos, size)
+	}
+	
+	let point = |[ x, y ]: [f32; 2]| pos + Vector2::new(size.x * x.clamp(0.0, 1.0), size.y * y.clamp(0.0, 1.0));
+	for (name, new) in positions {
+		let new = point(*new);
+		match view.bones.iter().find(|bone| bone.name == *name) {
+			Some(bone) => {
+				let from = point([ bone.x, bone.y ]);
+				draw.add_line(from, new, old, 1.0);
+				draw.add_circle(from, RADIUS, old, 16, 1.0);
+				draw.add_circle_filled(new, RADIUS, moved, 16);
+			},
+			None => draw.add_circle_filled(new, RADIUS, added, 16)
+		}
+		draw.add_circle(new, RADIUS, Color::BLACK, 16, 1.0);
+	}
+}
+
+// CSV import
+
+// Per-row preview of the changes a CSV would make, each of which can be left out.
+pub struct CsvImport {
+	path: PathBuf,
+	table: Result<CsvTable, String>,
+	pixels: bool,
+	resolution: [i32; 2],
+	changes: Option<Vec<CsvChange>>,
+	checked: Vec<bool>,
+	hide_unchanged: bool,
+	opening: bool
+}
+
+impl CsvImport {
+	pub fn open(path: PathBuf, resolution: [u32; 2]) -> Self {
+		let table = CsvTable::read(&path);
+		// Pixel columns are only used when the normalized ones are missing, until chosen otherwise.
+		let pixels = table.as_ref().is_ok_and(|table| !table.has_normalized());
+		Self {
+			path,
+			table,
+			pixels,
+			resolution: resolution.map(|x| x as i32),
+			changes: None,
+			checked: Vec::new(),
+			hide_unchanged: true,
+			opening: true
+		}
+	}
+	
+	// Draws the preview. Returns the changes to apply once confirmed.
+	pub fn draw(&mut self, ui: &Ui<App>, data: &SchemaData, open: &mut bool) -> Option<Vec<CsvChange>> {
+		const POPUP_NAME: &str = "Import CSV";
+		
+		let mut result = None;
+		*open = dialog::modal(ui, POPUP_NAME, std::mem::take(&mut self.opening), || {
+			let file = self.path.file_name().map(|x| x.to_string_lossy()).unwrap_or_default();
+			ui.text(&file);
+			
+			let table = match &self.table {
+				Ok(table) => table,
+				Err(err) => {
+					ui.text_colored(Color::new(1.0, 0.4, 0.4, 1.0), format!("Failed to read CSV: {}", err));
+					dialog::cancel_button(ui, "Close");
+					return;
+				}
+			};
+			
+			// Coordinates
+			
+			let mut replan = self.changes.is_none();
+			if table.has_normalized() && table.has_pixels() {
+				replan |= ui.checkbox("Use pixel coordinates", &mut self.pixels);
+			}
+			if self.pixels {
+				ui.set_next_item_width(200.0);
+				if ui.input_int_2_config("Resolution", &mut self.resolution).build() {
+					self.resolution = self.resolution.map(|x| x.max(1));
+					replan = true;
+				}
+			}
+			
+			if replan {
+				let coords = match self.pixels {
+					true => Coordinates::Pixels(self.resolution.map(|x| x as u32)),
+					false => Coordinates::Normalized
+				};
+				let changes = csv::plan(data, &table.rows(coords));
+				// Deletions are opt-in, rows are often left out of a sheet on purpose.
+				self.checked = changes.iter()
+					.map(|change| matches!(change.kind, ChangeKind::Add | ChangeKind::Update))
+					.collect();
+				self.changes = Some(changes);
+			}
+			let Some(changes) = &self.changes else { return };
+			
+			ui.checkbox("Hide unchanged", &mut self.hide_unchanged);
+			
+			// Rows
+			
+			let flags = TableFlags::RowBg | TableFlags::Borders | TableFlags::SizingFixedFit | TableFlags::ScrollY;
+			ui.table_config("##csv", 5)
+				.flags(flags)
+				.outer_size(Vector2::new(700.0, 400.0))
+				.with(|| {
+					for (i, change) in changes.iter().enumerate() {
+						if self.hide_unchanged && change.kind == ChangeKind::Unchanged { continue }
+						
+						ui.table_next_row(TableRowFlags::None, 0.0);
+						ui.table_next_column();
+						let selectable = !matches!(change.kind, ChangeKind::Unchanged | ChangeKind::Invalid);
+						ui.with_push(ItemId(i), || {
+							ui.with_disabled(!selectable, || {
+								ui.checkbox(change.kind.label(), &mut self.checked[i]);
+							});
+						});
+						
+						ui.table_next_column();
+						match change.line {
+							Some(line) => ui.text(&format!("Line {}", line)),
+							None => ui.text_disabled("Not in file")
+						}
+						
+						ui.table_next_column();
+						ui.text(&change.view);
+						
+						let bone = change.new.as_ref().or(change.old.as_ref());
+						ui.table_next_column();
+						ui.text(bone.map_or("", |bone| bone.label.as_str()));
+						
+						ui.table_next_column();
+						match (&change.error, &change.old, &change.new) {
+							(Some(err), _, _) => ui.text_colored(Color::new(1.0, 0.4, 0.4, 1.0), err),
+							(None, Some(old), Some(new)) => ui.text(&describe_update(old, new)),
+							(None, None, Some(new)) => ui.text(&format!("{} at {:.3}, {:.3}", new.name, new.x, new.y)),
+							(None, Some(old), None) => ui.text(&old.name),
+							_ => ()
+						}
+					}
+				});
+			
+			let count = |kind: ChangeKind| changes.iter().zip(&self.checked)
+				.filter(|(change, checked)| change.kind == kind && **checked)
+				.count();
+			let invalid = changes.iter().filter(|change| change.kind == ChangeKind::Invalid).count();
+			ui.text(&format!(
+				"{} to add, {} to update, {} to delete, {} invalid row(s)",
+				count(ChangeKind::Add), count(ChangeKind::Update), count(ChangeKind::Delete), invalid
+			));
+			ui.spacing();
+			
+			let any = self.checked.iter().any(|x| *x);
+			ui.with_disabled(!any, || {
+				if ui.button("Apply") {
+					ui.close_current_popup();
+					let checked = std::mem::take(&mut self.checked);
+					let changes = self.changes.take().unwrap_or_default();
+					result = Some(changes.into_iter().zip(checked)
+						.filter_map(|(change, checked)| checked.then_some(change))
+						.collect());
+				}
+			});
+			ui.same_line();
+			dialog::cancel_button(ui, "Cancel");
+		});
+		
+		result
+	}
+}
+
+fn describe_update(old: &SchemaBone, new: &SchemaBone) -> String {
+	let mut parts = Vec::new();
+	if old.label != new.label {
+		parts.push(format!("label \"{}\" -> \"{}\"", old.label, new.label));
+	}
+	if old.name != new.name {
+		parts.push(format!("name {} -> {}", old.name, new.name));
+	}
+	if old.x != new.x || old.y != new.y {
+		parts.push(format!("{:.3}, {:.3} -> {:.3}, {:.3}", old.x, old.y, new.x, new.y));
+	}
+	match parts.is_empty() {
+		true => new.name.clone(),
+		false => parts.join("; ")
+	}
+}
+
+// View import
+
+// Views from another schema, each previewed with its first image and copied in when checked.
+pub struct ViewImport {
+	path: PathBuf,
+	source: Result<SchemaData, String>,
+	checked: Vec<bool>,
+	// View shown in the preview.
+	focus: usize,
+	merge: bool,
+	opening: bool
+}
+
+impl ViewImport {
+	pub fn open(path: PathBuf) -> Self {
+		let source = SchemaData::try_deserialize(path.clone());
+		let checked = vec![ false; source.as_ref().map_or(0, |source| source.views.len()) ];
+		Self {
+			path,
+			source,
+			checked,
+			focus: 0,
+			merge: false,
+			opening: true
+		}
+	}
+	
+	pub fn focused(&self) -> Option<&SchemaView> {
+		self.source.as_ref().ok()?.views.get(self.focus)
+	}
+	
+	// Draws the view list. Returns the checked views and whether to merge them once confirmed.
+	pub fn draw(&mut self, ui: &Ui<App>, data: &SchemaData, image: &PreviewImage, open: &mut bool) -> Option<(Vec<SchemaView>, bool)> {
+		const POPUP_NAME: &str = "Import Views";
+		
+		let mut result = None;
+		*open = dialog::modal(ui, POPUP_NAME, std::mem::take(&mut self.opening), || {
+			let file = self.path.file_name().map(|x| x.to_string_lossy()).unwrap_or_default();
+			ui.text(&file);
+			
+			let source = match &self.source {
+				Ok(source) => source,
+				Err(err) => {
+					ui.text_colored(Color::new(1.0, 0.4, 0.4, 1.0), format!("Failed to read schema: {}", err));
+					dialog::cancel_button(ui, "Close");
+					return;
+				}
+			};
+			
+			if source.views.is_empty() {
+				ui.text_disabled("No views found in file.");
+				dialog::cancel_button(ui, "Close");
+				return;
+			}
+			
+			ui.checkbox("Merge bones into views with the same name", &mut self.merge);
+			
+			// Views
+			
+			let flags = TableFlags::RowBg | TableFlags::Borders | TableFlags::SizingFixedFit | TableFlags::ScrollY;
+			ui.table_config("##views", 3)
+				.flags(flags)
+				.outer_size(Vector2::new(400.0, PREVIEW_HEIGHT))
+				.with(|| {
+					for (i, view) in source.views.iter().enumerate() {
+						ui.table_next_row(TableRowFlags::None, 0.0);
+						ui.table_next_column();
+						ui.with_push(ItemId(i), || {
+							ui.checkbox("##import", &mut self.checked[i]);
+							ui.same_line();
+							if ui.selectable_config(&view.name).selected(self.focus == i).build() {
+								self.focus = i;
+							}
+						});
+						
+						ui.table_next_column();
+						ui.text(&format!("{} bone(s)", view.bones.len()));
+						
+						ui.table_next_column();
+						ui.text(&describe_view_import(data, view, self.merge));
+					}
+				});
+			
+			// Preview
+			
+			ui.same_line();
+			let aspect = image.dimensions[0] as f32 / image.dimensions[1].max(1) as f32;
+			let height = PREVIEW_HEIGHT.min(PREVIEW_WIDTH / aspect);
+			let size = Vector2::new(height * aspect, height);
+			let pos = ui.get_cursor_screen_pos();
+			ui.dummy(size);
+			if let Some(view) = source.views.get(self.focus) {
+				let existing = data.views.iter().find(|x| x.name == view.name && self.merge);
+				draw_view_preview(ui, pos, size, image, view, existing);
+			}
+			
+			let count = self.checked.iter().filter(|x| **x).count();
+			ui.text(&format!("{} view(s) to import", count));
+			ui.spacing();
+			
+			ui.with_disabled(count == 0, || {
+				if ui.button("Import") {
+					ui.close_current_popup();
+					let views = source.views.iter().zip(&self.checked)
+						.filter(|(_, checked)| **checked)
+						.map(|(view, _)| view.clone())
+						.collect();
+					result = Some((views, self.merge));
+				}
+			});
+			ui.same_line();
+			dialog::cancel_button(ui, "Cancel");
+		});
+		
+		result
+	}
+}
+
+// SVG import
+
+// Bone positions read back from an exported SVG, previewed per bone before they're moved.
+// Circles match bones by `data-name` and `data-occurrence` first, by id when those are missing.
+pub struct SvgImport {
+	path: PathBuf,
+	layout: Result<SvgLayout, String>,
+	// View the positions go to, the one the file was exported from when it's still there.
+	view: Option<String>,
+	moves: Option<(Vec<SvgMove>, Vec<String>)>,
+	checked: Vec<bool>,
+	opening: bool
+}
+
+impl SvgImport {
+	pub fn open(path: PathBuf, data: &SchemaData, current: Option<&str>) -> Self {
+		let layout = SvgLayout::read(&path);
+		let view = layout.as_ref().ok()
+			.and_then(|layout| layout.view.as_deref())
+			.filter(|name| data.views.iter().any(|view| view.name == *name))
+			.or(current)
+			.map(str::to_string);
+		Self {
+			path,
+			layout,
+			view,
+			moves: None,
+			checked: Vec::new(),
+			opening: true
+		}
+	}
+	
+	// Draws the preview. Returns the view and the moves to apply to it once confirmed.
+	pub fn draw(&mut self, ui: &Ui<App>, data: &SchemaData, open: &mut bool) -> Option<(String, Vec<SvgMove>)> {
+		const POPUP_NAME: &str = "Import SVG";
+		
+		let mut result = None;
+		*open = dialog::modal(ui, POPUP_NAME, std::mem::take(&mut self.opening), || {
+			let file = self.path.file_name().map(|x| x.to_string_lossy()).unwrap_or_default();
+			ui.text(&file);
+			
+			let layout = match &self.layout {
+				Ok(layout) => layout,
+				Err(err) => {
+					ui.text_colored(Color::new(1.0, 0.4, 0.4, 1.0), format!("Failed to read SVG: {}", err));
+					dialog::cancel_button(ui, "Close");
+					return;
+				}
+			};
+			
+			// Target view
+			
+			let preview = self.view.clone().unwrap_or_default();
+			ui.set_next_item_width(200.0);
+			ui.combo_config("View").preview_value(&preview).with(|| {
+				for view in &data.views {
+					if ui.selectable_config(&view.name).selected(self.view.as_ref() == Some(&view.name)).build() {
+						self.view = Some(view.name.clone());
+						self.moves = None;
+					}
+				}
+			});
+			if let Some(source) = layout.view.as_ref().filter(|source| self.view.as_ref() != Some(*source)) {
+				ui.text_disabled(format!("Exported from view \"{}\".", source));
+			}
+			
+			let Some(view) = data.views.iter().find(|view| Some(&view.name) == self.view.as_ref()) else { return };
+			if self.moves.is_none() {
+				let (moves, unmatched) = layout.plan(view);
+				self.checked = moves.iter().map(SvgMove::is_moved).collect();
+				self.moves = Some((moves, unmatched));
+			}
+			let Some((moves, unmatched)) = &self.moves else { return };
+			
+			// Rows
+			
+			let flags = TableFlags::RowBg | TableFlags::Borders | TableFlags::SizingFixedFit | TableFlags::ScrollY;
+			ui.table_config("##svg", 3)
+				.flags(flags)
+				.outer_size(Vector2::new(500.0, 300.0))
+				.with(|| {
+					for (i, change) in moves.iter().enumerate() {
+						if !change.is_moved() { continue }
+						
+						ui.table_next_row(TableRowFlags::None, 0.0);
+						ui.table_next_column();
+						ui.with_push(ItemId(i), || {
+							ui.checkbox(&change.label, &mut self.checked[i]);
+						});
+						
+						ui.table_next_column();
+						ui.text(&change.name);
+						
+						ui.table_next_column();
+						let ([ x0, y0 ], [ x1, y1 ]) = (change.from, change.to);
+						ui.text(&format!("{:.3}, {:.3} -> {:.3}, {:.3}", x0, y0, x1, y1));
+					}
+				});
+			
+			let unchanged = moves.iter().filter(|change| !change.is_moved()).count();
+			let count = self.checked.iter().filter(|x| **x).count();
+			ui.text(&format!("{} to move, {} unchanged", count, unchanged));
+			ui.text_disabled("Circles match bones by data-name and data-occurrence, then by id.");
+			if !unmatched.is_empty() {
+				ui.text_disabled(format!("No bone, or one already matched, for {} circle(s): {}", unmatched.len(), unmatched.join(", ")));
+			}
+			ui.spacing();
+			
+			ui.with_disabled(count == 0, || {
+				if ui.button("Apply") {
+					ui.close_current_popup();
+					let checked = std::mem::take(&mut self.checked);
+					let (moves, _) = self.moves.take().unwrap_or_default();
+					let moves = moves.into_iter().zip(checked)
+						.filter(|(_, checked)| *checked)
+						.map(|(change, _)| change)
+						.collect();
+					result = Some((view.name.clone(), moves));
+				}
+			});
+			ui.same_line();
+			dialog::cancel_button(ui, "Cancel");
+		});
+		
+		result
+	}
+}
+
+// What importing `view` will do to the schema.
+fn describe_view_import(data: &SchemaData, view: &SchemaView, merge: bool) -> String {
+	match data.views.iter().find(|x| x.name == view.name) {
+		None => "New view".to_string(),
+		Some(existing) if merge => {
+			let added = view.bones.iter().filter(|bone| !existing.bones.iter().any(|x| x.name == bone.name)).count();
+			format!("Merge, {} new bone(s)", added)
+		},
+		Some(_) => format!("Rename to \"{}\"", data.unique_view_name(&view.name))
+	}
+}
+
+// Bones a merge would skip, already being in `existing`, are drawn hollow.
+fn draw_view_preview(ui: &Ui<App>, pos: Vector2, size: Vector2, image: &PreviewImage, view: &SchemaView, existing: Option<&SchemaView>) {
+	const RADIUS: f32 = 4.0;
+	let color = Color::new(0.4, 1.0, 0.5, 1.0);
+	let skipped = Color::new(0.7, 0.7, 0.7, 0.8);
+	
+	let draw = ui.window_draw_list();
+	match image.texture {
+		Some(tex) => draw.add_image(tex, pos, pos + size, Vector2::new(0.0, 0.0), Vector2::new(1.0, 1.0), Color::WHITE),
+		None => canvas::draw_checkerboard(ui, pos, size)
+	}
+	
+	let mouse = ui.io().MousePos;
+	for bone in &view.bones {
+		let point = pos + Vector2::new(size.x * bone.x, size.y * bone.y);
+		match existing.is_some_and(|view| view.bones.iter().any(|x| x.name == bone.name)) {
+			true => draw.add_circle(point, RADIUS, skipped, 16, 1.5),
+			false => {
+				draw.add_circle_filled(point, RADIUS, color, 16);
+				draw.add_circle(point, RADIUS, Color::BLACK, 16, 1.0);
+			}
+		}
+		
+		if (mouse.x - point.x).abs() <= RADIUS && (mouse.y - point.y).abs() <= RADIUS {
+			ui.with_tooltip(|| ui.text(&format!("{} ({})", bone.label, bone.name)));
+		}
+	}
+}
\ No newline at end of file
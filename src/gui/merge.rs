@@ -0,0 +1,87 @@
+﻿use crate::{
+	app::App,
+	gui::dialog,
+	schema::{
+		data::SchemaData,
+		merge::{SchemaMerge, Side}
+	}
+};
+use easy_imgui::{ItemId, TableFlags, TableRowFlags, Ui, Vector2};
+
+// Merge resolver
+
+pub struct MergeResolver {
+	// Document the merge is applied to, which stays the same when another tab is selected.
+	pub doc: usize,
+	merge: SchemaMerge,
+	opening: bool
+}
+
+impl MergeResolver {
+	pub fn open(doc: usize, merge: SchemaMerge) -> Self {
+		Self { doc, merge, opening: true }
+	}
+	
+	// Draws the conflicts. Returns the merged schema once applied.
+	pub fn draw(&mut self, ui: &Ui<App>, open: &mut bool) -> Option<SchemaData> {
+		const POPUP_NAME: &str = "Resolve Merge";
+		
+		let mut result = None;
+		*open = dialog::modal(ui, POPUP_NAME, std::mem::take(&mut self.opening), || {
+			if self.merge.is_clean() {
+				ui.text("No conflicts, both sides merge cleanly.");
+			} else {
+				ui.text(&format!("{} conflict(s). Pick the side to keep for each:", self.merge.conflicts.len()));
+				if ui.button("All ours") {
+					self.merge.pick_all(Side::Ours);
+				}
+				ui.same_line();
+				if ui.button("All theirs") {
+					self.merge.pick_all(Side::Theirs);
+				}
+				
+				// Rows
+				
+				let flags = TableFlags::RowBg | TableFlags::Borders | TableFlags::SizingFixedFit | TableFlags::ScrollY;
+				ui.table_config("##conflicts", 5)
+					.flags(flags)
+					.outer_size(Vector2::new(700.0, 400.0))
+					.with(|| {
+						for (i, conflict) in self.merge.conflicts.iter_mut().enumerate() {
+							ui.table_next_row(TableRowFlags::None, 0.0);
+							ui.with_push(ItemId(i), || {
+								ui.table_next_column();
+								ui.text(conflict.view.as_deref().unwrap_or(""));
+								
+								ui.table_next_column();
+								ui.text(conflict.bone.as_deref().unwrap_or(""));
+								
+								ui.table_next_column();
+								ui.text(conflict.field.label());
+								
+								ui.table_next_column();
+								if ui.radio_button_config(format!("Ours: {}", conflict.ours), conflict.side == Side::Ours).build() {
+									conflict.side = Side::Ours;
+								}
+								
+								ui.table_next_column();
+								if ui.radio_button_config(format!("Theirs: {}", conflict.theirs), conflict.side == Side::Theirs).build() {
+									conflict.side = Side::Theirs;
+								}
+							});
+						}
+					});
+			}
+			ui.spacing();
+			
+			if ui.button("Apply") {
+				ui.close_current_popup();
+				result = Some(self.merge.result());
+			}
+			ui.same_line();
+			dialog::cancel_button(ui, "Cancel");
+		});
+		
+		result
+	}
+}
\ No newline at end of file
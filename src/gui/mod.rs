@@ -1,32 +1,54 @@
-﻿mod editor;
+﻿mod canvas;
+mod dialog;
+mod diff;
+mod editor;
+mod export;
+mod history;
 mod images;
+mod import;
+mod merge;
+mod theme;
 
 use crate::{
 	app::App,
+	config::{Configuration, Theme, UiPreferences, WindowState},
 	gui::{
-		editor::Editor,
+		editor::{Editor, FileAction},
 		images::ImageLoader
-	}
+	},
+	keys::{Action, KeyBind, Keymap}
 };
 use std::{cell::RefCell, rc::Rc};
-use easy_imgui::{Cond, Ui, Key, Vector2, WindowFlags};
+use easy_imgui::{easy_imgui_sys::ImGuiStyle, style::Style, Color, Cond, FontAtlasMut, InputTextFlags, ItemId, Ui, Key, Vector2, TableFlags, TableRowFlags, WindowFlags};
+use easy_imgui_renderer::glr::GlContext;
+use rfd::FileDialog;
 
 const VEC_ZERO: Vector2 = Vector2::new(0.0, 0.0);
 
 pub struct Gui {
 	pub images: Rc<RefCell<ImageLoader>>,
 	editor: Editor,
-	img_path_opening: bool
+	img_path_opening: bool,
+	shortcuts_opening: bool,
+	capturing: Option<Action>,
+	appearance_opening: bool,
+	style_changed: bool,
+	new_language: String
 }
 
 impl Gui {
-	pub fn new() -> Self {
-		let images = ImageLoader::new();
+	pub fn new(gl: GlContext, cfg: Configuration) -> Self {
+		let images = ImageLoader::new(gl);
 		let images_ref = Rc::new(RefCell::new(images));
 		Self {
 			images: images_ref.clone(),
-			editor: Editor::new(images_ref),
-			img_path_opening: false
+			editor: Editor::new(images_ref, cfg),
+			img_path_opening: false,
+			shortcuts_opening: false,
+			capturing: None,
+			appearance_opening: false,
+			style_changed: false,
+			new_language: String::new()
 		}
 	}
 	
@@ -34,39 +56,163 @@ impl Gui {
 		ui.set_next_window_pos(VEC_ZERO, Cond::Always, VEC_ZERO);
 		ui.set_next_window_size(ui.display_size(), Cond::Always);
 		ui.window_config("##main")
-			.flags(WindowFlags::MenuBar | WindowFlags::NoDecoration)
+			// Stays behind floating panels such as the diff when clicked.
+			.flags(WindowFlags::MenuBar | WindowFlags::NoDecoration | WindowFlags::NoBringToFrontOnFocus)
 			.with_always(|_| {
 				self.draw_main(ui);
 			});
 		self.draw_popups(ui);
 	}
 	
-	pub fn close(&mut self) {
+	pub fn title(&self) -> String {
+		self.editor.title()
+	}
+	
+	pub fn request_quit(&mut self) {
+		self.editor.request(FileAction::Quit);
+	}
+	
+	pub fn should_quit(&self) -> bool {
+		self.editor.should_quit()
+	}
+	
+	// Appearance
+	
+	pub fn apply_style(&self, style: &mut Style, base: &ImGuiStyle) {
+		theme::apply_style(style, base, &self.editor.cfg.ui);
+	}
+	
+	pub fn take_style_changed(&mut self) -> bool {
+		std::mem::take(&mut self.style_changed)
+	}
+	
+	pub fn build_fonts(&self, atlas: &mut FontAtlasMut<'_, App>) {
+		theme::build_fonts(atlas, &self.editor.cfg.ui);
+	}
+	
+	pub fn close(&mut self, window: WindowState) {
+		self.editor.cfg.window = Some(window);
 		self.editor.save_config();
 	}
 	
 	fn draw_main(&mut self, ui: &Ui<App>) {
 		ui.with_always_menu_bar(|_| self.draw_menu_bar(ui));
-		self.editor.draw(&ui);
+		self.editor.draw(ui);
 	}
 	
 	fn draw_menu_bar(&mut self, ui: &Ui<App>) {
 		ui.menu_config("File").with(|| {
-			if ui.menu_item_config("New").build() {
-				self.editor.new_file()
-			}
-			if ui.menu_item_config("Open").build() {
-				self.editor.open_file();
+			self.action_item(ui, Action::New, true);
+			self.action_item(ui, Action::Open, true);
+			self.action_item(ui, Action::Close, self.editor.is_file_loaded());
+			
+			let recent = self.editor.cfg.recent_files.clone();
+			ui.menu_config("Open Recent").enabled(!recent.is_empty()).with(|| {
+				for (i, path) in recent.into_iter().enumerate() {
+					let label = format!("{}##recent_{}", path.display(), i);
+					if ui.menu_item_config(label).enabled(path.exists()).build() {
+						self.editor.request(FileAction::OpenPath(path));
+					}
+				}
+				
+				ui.separator();
+				
+				if ui.menu_item_config("Clear Recent").build() {
+					self.editor.cfg.recent_files.clear();
+				}
+			});
+			
+			ui.separator();
+			
+			let file_loaded = self.editor.is_file_loaded();
+			self.action_item(ui, Action::Save, file_loaded);
+			self.action_item(ui, Action::SaveAs, file_loaded);
+			
+			ui.separator();
+			
+			let has_view = self.editor.has_view();
+			ui.menu_config("Import").enabled(file_loaded).with(|| {
+				if ui.menu_item_config("Bone List...").enabled(has_view).build() {
+					self.editor.import_bone_list();
+				}
+				if ui.menu_item_config("3D Pose...").enabled(has_view).build() {
+					self.editor.import_pose();
+				}
+				if ui.menu_item_config("CSV...").build() {
+					self.editor.import_csv();
+				}
+				if ui.menu_item_config("Views...").build() {
+					self.editor.import_views();
+				}
+				if ui.menu_item_config("SVG...").build() {
+					self.editor.import_svg();
+				}
+			});
+			ui.menu_config("Export").enabled(file_loaded).with(|| {
+				if ui.menu_item_config("CSV...").build() {
+					self.editor.export_csv();
+				}
+				if ui.menu_item_config("Preview...").enabled(has_view).build() {
+					self.editor.export_preview();
+				}
+				if ui.menu_item_config("SVG...").enabled(has_view).build() {
+					self.editor.export_svg();
+				}
+				if ui.menu_item_config("HTML...").build() {
+					self.editor.export_html();
+				}
+			});
+			if ui.menu_item_config("Merge...").enabled(file_loaded).build() {
+				self.editor.merge_file();
 			}
+		});
+		
+		ui.menu_config("Edit").with(|| {
+			self.action_item(ui, Action::Undo, self.editor.can_undo());
+			self.action_item(ui, Action::Redo, self.editor.can_redo());
 			
 			ui.separator();
 			
+			self.action_item(ui, Action::Copy, self.editor.has_view());
+			self.action_item(ui, Action::Paste, self.editor.can_paste());
+			
+			ui.separator();
+			
+			self.action_item(ui, Action::AddBone, self.editor.has_view());
+			self.action_item(ui, Action::DeleteBone, self.editor.has_bone());
+		});
+		
+		ui.menu_config("View").with(|| {
 			let file_loaded = self.editor.is_file_loaded();
-			if ui.menu_item_config("Save").enabled(file_loaded).build() {
-				self.editor.save_file(false);
+			self.action_item(ui, Action::NextView, file_loaded);
+			self.action_item(ui, Action::PrevView, file_loaded);
+			
+			let has_view = self.editor.has_view();
+			self.action_item(ui, Action::NextTab, has_view);
+			self.action_item(ui, Action::PrevTab, has_view);
+			
+			ui.separator();
+			
+			self.action_item(ui, Action::ZoomIn, has_view);
+			self.action_item(ui, Action::ZoomOut, has_view);
+			self.action_item(ui, Action::ZoomReset, has_view);
+			
+			ui.separator();
+			
+			ui.menu_config("Language").enabled(file_loaded).with(|| {
+				self.draw_language_menu(ui);
+			});
+			if ui.menu_item_config("Missing Translations...").enabled(file_loaded).build() {
+				self.editor.open_translations();
 			}
-			if ui.menu_item_config("Save As").enabled(file_loaded).build() {
-				self.editor.save_file(true);
+			
+			ui.separator();
+			
+			if ui.menu_item_config("Schema Diff").enabled(file_loaded).build() {
+				self.editor.open_diff();
+			}
+			if ui.menu_item_config("Diff Overlay").selected(self.editor.diff_overlay()).enabled(file_loaded).build() {
+				self.editor.toggle_diff_overlay();
 			}
 		});
 		
@@ -74,10 +220,81 @@ impl Gui {
 			if ui.menu_item_config("Set image path...").build() {
 				self.img_path_opening = true;
 			}
+			if ui.menu_item_config("Keyboard Shortcuts...").build() {
+				self.shortcuts_opening = true;
+			}
+			if ui.menu_item_config("Appearance...").build() {
+				self.appearance_opening = true;
+			}
+			
+			ui.separator();
+			
+			let mut budget = self.editor.cfg.image_budget as i32;
+			ui.set_next_item_width(120.0);
+			if ui.input_int_config("Image memory (MB)", &mut budget).step(64).build() {
+				self.editor.set_image_budget(budget.max(16) as usize);
+			}
+			
+			let usage = self.editor.image_memory_usage() / (1024 * 1024);
+			ui.text_disabled(format!("{} MB in use", usage));
+			
+			ui.separator();
+			
+			let mut aspect = self.editor.cfg.blank_aspect.map(|x| x as i32);
+			ui.set_next_item_width(120.0);
+			if ui.input_int_2_config("Blank canvas ratio", &mut aspect).build() {
+				self.editor.cfg.blank_aspect = aspect.map(|x| x.max(1) as u32);
+			}
 		});
 	}
 	
+	fn draw_language_menu(&mut self, ui: &Ui<App>) {
+		let current = self.editor.cfg.ui.language.clone();
+		
+		let fallback = match self.editor.fallback_language() {
+			Some(lang) => format!("{} (fallback)", lang),
+			None => "Fallback".to_string()
+		};
+		if ui.menu_item_config(fallback).selected(current.is_none()).build() {
+			self.editor.set_language(None);
+		}
+		
+		ui.separator();
+		
+		for lang in self.editor.languages() {
+			let is_select = current.as_ref() == Some(&lang);
+			if ui.menu_item_config(lang.as_str()).selected(is_select).build() {
+				self.editor.set_language(Some(lang));
+			}
+		}
+		
+		ui.separator();
+		
+		// Language codes follow the schema, e.g. "ja" for `label.ja`.
+		ui.set_next_item_width(120.0);
+		let add = ui.input_text_hint_config("##new_lang", "Add language", &mut self.new_language)
+			.flags(InputTextFlags::EnterReturnsTrue)
+			.build();
+		if add && !self.new_language.trim().is_empty() {
+			self.editor.set_language(Some(self.new_language.trim().to_string()));
+			self.new_language.clear();
+			ui.close_current_popup();
+		}
+	}
+	
+	// Menu item that shows and runs the shortcut for `action`.
+	fn action_item(&mut self, ui: &Ui<App>, action: Action, enabled: bool) {
+		let shortcut = self.editor.cfg.keys.shortcut(action);
+		if ui.menu_item_config(action.label()).shortcut(shortcut).enabled(enabled).build() {
+			self.editor.run_action(action);
+		}
+	}
+	
 	fn draw_popups(&mut self, ui: &Ui<App>) {
+		self.editor.draw_prompts(ui);
+		self.draw_shortcuts(ui);
+		self.draw_appearance(ui);
+		
 		const POPUP_NAME: &str = "Set image path";
 		
 		if self.img_path_opening {
@@ -107,8 +324,206 @@ impl Gui {
 				
 				if ui.is_key_pressed(Key::Enter) {
 					ui.close_current_popup();
+					self.editor.cfg.image_path_override = None;
 					self.editor.cfg.save();
 				}
 			});
 	}
+	
+	fn draw_shortcuts(&mut self, ui: &Ui<App>) {
+		const POPUP_NAME: &str = "Keyboard Shortcuts";
+		
+		if self.shortcuts_opening {
+			ui.open_popup(POPUP_NAME);
+			self.shortcuts_opening = false;
+			self.capturing = None;
+		}
+		
+		ui.popup_modal_config(POPUP_NAME)
+			.close_button(true)
+			.flags(WindowFlags::AlwaysAutoResize)
+			.with(|| {
+				let keys = &mut self.editor.cfg.keys;
+				
+				// Escape cancels rebinding, so it can't be bound itself.
+				if let Some(action) = self.capturing {
+					if ui.is_key_pressed(Key::Escape) {
+						self.capturing = None;
+					} else if let Some(bind) = KeyBind::capture(ui) {
+						keys.set(action, Some(bind));
+						self.capturing = None;
+					}
+				}
+				
+				let flags = TableFlags::RowBg | TableFlags::Borders | TableFlags::SizingFixedFit;
+				ui.table_config("##shortcuts", 3).flags(flags).with(|| {
+					for (i, action) in Action::ALL.into_iter().enumerate() {
+						ui.table_next_row(TableRowFlags::None, 0.0);
+						ui.table_next_column();
+						ui.text(action.label());
+						
+						ui.table_next_column();
+						if self.capturing == Some(action) {
+							ui.text_disabled("Press a key...");
+						} else {
+							match keys.get(action) {
+								Some(bind) => ui.text(&bind.to_string()),
+								None => ui.text_disabled("None")
+							}
+						}
+						
+						let conflicts = keys.conflicts(action);
+						if !conflicts.is_empty() {
+							let names: Vec<&str> = conflicts.iter().map(|x| x.label()).collect();
+							ui.text_colored(Color::new(1.0, 0.4, 0.4, 1.0), format!("Conflicts with {}", names.join(", ")));
+						}
+						
+						ui.table_next_column();
+						ui.with_push(ItemId(i), || {
+							if ui.button("Edit") {
+								self.capturing = Some(action);
+							}
+							ui.same_line();
+							if ui.button("Clear") {
+								keys.set(action, None);
+							}
+						});
+					}
+				});
+				
+				ui.spacing();
+				
+				if ui.button("Reset to defaults") {
+					*keys = Keymap::default();
+					self.capturing = None;
+				}
+				ui.same_line();
+				if ui.button("Close") {
+					ui.close_current_popup();
+					self.capturing = None;
+					self.editor.cfg.save();
+				}
+			});
+	}
+	
+	fn draw_appearance(&mut self, ui: &Ui<App>) {
+		const POPUP_NAME: &str = "Appearance";
+		
+		if self.appearance_opening {
+			ui.open_popup(POPUP_NAME);
+			self.appearance_opening = false;
+		}
+		
+		ui.popup_modal_config(POPUP_NAME)
+			.close_button(true)
+			.flags(WindowFlags::AlwaysAutoResize)
+			.with(|| {
+				let prefs = &mut self.editor.cfg.ui;
+				
+				// Sliders apply on release, rebuilding the font atlas on every step is slow.
+				
+				ui.separator_text("Interface");
+				
+				ui.set_next_item_width(200.0);
+				ui.slider_float_config("UI scale", &mut prefs.scale)
+					.range(theme::SCALE_RANGE.0, theme::SCALE_RANGE.1)
+					.build();
+				if ui.is_item_deactivated_after_edit() {
+					self.style_changed = true;
+					ui.invalidate_font_atlas();
+				}
+				
+				let font = match &prefs.font {
+					Some(path) => path.display().to_string(),
+					None => "Default".to_string()
+				};
+				ui.text(&format!("Font: {}", font));
+				if ui.button("Browse...") {
+					let path = FileDialog::new()
+						.add_filter("Fonts", &["ttf", "otf", "ttc"])
+						.pick_file();
+					if path.is_some() {
+						prefs.font = path;
+						ui.invalidate_font_atlas();
+					}
+				}
+				ui.same_line();
+				ui.with_disabled(prefs.font.is_none(), || {
+					if ui.button("Use default") {
+						prefs.font = None;
+						ui.invalidate_font_atlas();
+					}
+				});
+				
+				ui.set_next_item_width(200.0);
+				ui.slider_float_config("Font size", &mut prefs.font_size)
+					.range(theme::FONT_SIZE_RANGE.0, theme::FONT_SIZE_RANGE.1)
+					.build();
+				if ui.is_item_deactivated_after_edit() {
+					ui.invalidate_font_atlas();
+				}
+				
+				ui.separator_text("Theme");
+				
+				for (label, theme) in [ ("Dark", Theme::Dark), ("Light", Theme::Light), ("Custom", Theme::Custom) ] {
+					if ui.radio_button_config(label, prefs.theme == theme).build() {
+						prefs.theme = theme;
+						self.style_changed = true;
+					}
+					ui.same_line();
+				}
+				ui.new_line();
+				
+				if prefs.theme == Theme::Custom {
+					let custom = &mut prefs.custom_theme;
+					for (label, value) in [
+						("Background", &mut custom.background),
+						("Text", &mut custom.text),
+						("Frame", &mut custom.frame),
+						("Accent", &mut custom.accent)
+					] {
+						if Self::color_edit(ui, label, value) {
+							self.style_changed = true;
+						}
+					}
+				}
+				
+				ui.separator_text("Bone markers");
+				
+				ui.set_next_item_width(200.0);
+				ui.slider_float_config("Size", &mut prefs.marker_radius)
+					.range(3.0, 30.0)
+					.build();
+				Self::color_edit(ui, "Color", &mut prefs.marker_color);
+				Self::color_edit(ui, "Selected color", &mut prefs.marker_selected_color);
+				
+				ui.spacing();
+				
+				if ui.button("Reset to defaults") {
+					*prefs = UiPreferences {
+						compare_opacity: prefs.compare_opacity,
+						// Picked from the Language menu, not this window.
+						language: prefs.language.take(),
+						..UiPreferences::default()
+					};
+					self.style_changed = true;
+					ui.invalidate_font_atlas();
+				}
+				ui.same_line();
+				if ui.button("Close") {
+					ui.close_current_popup();
+					self.editor.cfg.save();
+				}
+			});
+	}
+	
+	fn color_edit(ui: &Ui<App>, label: &str, value: &mut [f32; 4]) -> bool {
+		let mut color = theme::color(*value);
+		ui.set_next_item_width(200.0);
+		let changed = ui.color_edit_4_config(label, &mut color).build();
+		if changed {
+			*value = *color.as_ref();
+		}
+		changed
+	}
 }
\ No newline at end of file
@@ -0,0 +1,109 @@
+﻿use crate::config::{CustomTheme, Theme, UiPreferences};
+use std::{fs, path::Path};
+use easy_imgui::{easy_imgui_sys::ImGuiStyle, style::Style, Color, ColorId, FontAtlasMut, FontInfo};
+
+pub const SCALE_RANGE: (f32, f32) = (0.5, 3.0);
+pub const FONT_SIZE_RANGE: (f32, f32) = (8.0, 32.0);
+
+// Style
+
+// Starts over from `base` each time, so scaling doesn't compound.
+pub fn apply_style(style: &mut Style, base: &ImGuiStyle, prefs: &UiPreferences) {
+	*style.get_mut() = *base;
+	
+	match prefs.theme {
+		Theme::Dark => style.set_colors_dark(),
+		Theme::Light => style.set_colors_light(),
+		Theme::Custom => {
+			style.set_colors_dark();
+			apply_custom(style, &prefs.custom_theme);
+		}
+	}
+	
+	let scale = prefs.scale.clamp(SCALE_RANGE.0, SCALE_RANGE.1);
+	// SAFETY: only multiplies the size fields of the style.
+	unsafe { style.get_mut().ScaleAllSizes(scale) };
+}
+
+fn apply_custom(style: &mut Style, theme: &CustomTheme) {
+	let background = color(theme.background);
+	let frame = color(theme.frame);
+	let accent = color(theme.accent);
+	let text = color(theme.text);
+	
+	for id in [ ColorId::WindowBg, ColorId::ChildBg, ColorId::MenuBarBg, ColorId::TitleBg ] {
+		style.set_color(id, background);
+	}
+	style.set_color(ColorId::PopupBg, shade(background, 1.1));
+	
+	style.set_color(ColorId::Text, text);
+	style.set_color(ColorId::TextDisabled, Color::new(text.r, text.g, text.b, text.a * 0.5));
+	
+	style.set_color(ColorId::FrameBg, frame);
+	style.set_color(ColorId::FrameBgHovered, shade(frame, 1.2));
+	style.set_color(ColorId::FrameBgActive, shade(frame, 1.4));
+	
+	for (id, factor) in [
+		(ColorId::Button, 0.8),
+		(ColorId::ButtonHovered, 1.0),
+		(ColorId::ButtonActive, 1.15),
+		(ColorId::Header, 0.7),
+		(ColorId::HeaderHovered, 0.9),
+		(ColorId::HeaderActive, 1.0),
+		(ColorId::Tab, 0.6),
+		(ColorId::TabHovered, 1.0),
+		(ColorId::TabSelected, 0.85),
+		(ColorId::CheckMark, 1.15),
+		(ColorId::SliderGrab, 1.0),
+		(ColorId::SliderGrabActive, 1.15),
+		(ColorId::TitleBgActive, 0.6)
+	] {
+		style.set_color(id, shade(accent, factor));
+	}
+}
+
+pub fn color([ r, g, b, a ]: [f32; 4]) -> Color {
+	Color::new(r, g, b, a)
+}
+
+fn shade(color: Color, factor: f32) -> Color {
+	Color::new(
+		(color.r * factor).min(1.0),
+		(color.g * factor).min(1.0),
+		(color.b * factor).min(1.0),
+		color.a
+	)
+}
+
+// Fonts
+
+pub fn build_fonts<A>(atlas: &mut FontAtlasMut<'_, A>, prefs: &UiPreferences) {
+	let scale = prefs.scale.clamp(SCALE_RANGE.0, SCALE_RANGE.1);
+	let size = prefs.font_size.clamp(FONT_SIZE_RANGE.0, FONT_SIZE_RANGE.1) * scale;
+	
+	if let Some(path) = &prefs.font {
+		match read_font(path) {
+			Ok(ttf) => {
+				atlas.add_font(FontInfo::new(ttf, size));
+				return;
+			},
+			Err(err) => println!("Failed to load font {}: {}", path.display(), err)
+		}
+	}
+	
+	atlas.add_font(FontInfo::default_font(size));
+}
+
+// ImGui aborts on data it can't parse, so anything that isn't a font is refused up front.
+fn read_font(path: &Path) -> Result<Vec<u8>, String> {
+	let ttf = fs::read(path).map_err(|err| err.to_string())?;
+	
+	let is_font = matches!(
+		ttf.get(0..4),
+		Some([ 0x00, 0x01, 0x00, 0x00 ] | b"OTTO" | b"true" | b"ttcf")
+	);
+	match is_font {
+		true => Ok(ttf),
+		false => Err("not a TrueType or OpenType font".to_string())
+	}
+}
\ No newline at end of file
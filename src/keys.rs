@@ -0,0 +1,322 @@
+﻿use std::{collections::BTreeMap, fmt};
+use easy_imgui::{Key, Ui};
+use serde::{Serialize, Deserialize};
+
+// Action
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Action {
+	New,
+	Open,
+	Close,
+	Save,
+	SaveAs,
+	Undo,
+	Redo,
+	Copy,
+	Paste,
+	AddBone,
+	DeleteBone,
+	NextView,
+	PrevView,
+	NextTab,
+	PrevTab,
+	ZoomIn,
+	ZoomOut,
+	ZoomReset,
+	NudgeLeft,
+	NudgeRight,
+	NudgeUp,
+	NudgeDown
+}
+
+impl Action {
+	pub const ALL: [Action; 22] = [
+		Action::New,
+		Action::Open,
+		Action::Close,
+		Action::Save,
+		Action::SaveAs,
+		Action::Undo,
+		Action::Redo,
+		Action::Copy,
+		Action::Paste,
+		Action::AddBone,
+		Action::DeleteBone,
+		Action::NextView,
+		Action::PrevView,
+		Action::NextTab,
+		Action::PrevTab,
+		Action::ZoomIn,
+		Action::ZoomOut,
+		Action::ZoomReset,
+		Action::NudgeLeft,
+		Action::NudgeRight,
+		Action::NudgeUp,
+		Action::NudgeDown
+	];
+	
+	// Name used in the configuration file.
+	pub fn id(self) -> &'static str {
+		match self {
+			Action::New => "new",
+			Action::Open => "open",
+			Action::Close => "close",
+			Action::Save => "save",
+			Action::SaveAs => "save_as",
+			Action::Undo => "undo",
+			Action::Redo => "redo",
+			Action::Copy => "copy",
+			Action::Paste => "paste",
+			Action::AddBone => "add_bone",
+			Action::DeleteBone => "delete_bone",
+			Action::NextView => "next_view",
+			Action::PrevView => "prev_view",
+			Action::NextTab => "next_tab",
+			Action::PrevTab => "prev_tab",
+			Action::ZoomIn => "zoom_in",
+			Action::ZoomOut => "zoom_out",
+			Action::ZoomReset => "zoom_reset",
+			Action::NudgeLeft => "nudge_left",
+			Action::NudgeRight => "nudge_right",
+			Action::NudgeUp => "nudge_up",
+			Action::NudgeDown => "nudge_down"
+		}
+	}
+	
+	pub fn label(self) -> &'static str {
+		match self {
+			Action::New => "New",
+			Action::Open => "Open",
+			Action::Close => "Close",
+			Action::Save => "Save",
+			Action::SaveAs => "Save As",
+			Action::Undo => "Undo",
+			Action::Redo => "Redo",
+			Action::Copy => "Copy Bones",
+			Action::Paste => "Paste Bones",
+			Action::AddBone => "Add Bone",
+			Action::DeleteBone => "Delete Bone",
+			Action::NextView => "Next View",
+			Action::PrevView => "Previous View",
+			Action::NextTab => "Next Tab",
+			Action::PrevTab => "Previous Tab",
+			Action::ZoomIn => "Zoom In",
+			Action::ZoomOut => "Zoom Out",
+			Action::ZoomReset => "Reset Zoom",
+			Action::NudgeLeft => "Nudge Left",
+			Action::NudgeRight => "Nudge Right",
+			Action::NudgeUp => "Nudge Up",
+			Action::NudgeDown => "Nudge Down"
+		}
+	}
+	
+	pub fn from_id(id: &str) -> Option<Action> {
+		Self::ALL.into_iter().find(|action| action.id() == id)
+	}
+	
+	// File actions still work while typing into a text field.
+	pub fn is_global(self) -> bool {
+		matches!(self, Action::New | Action::Open | Action::Close | Action::Save | Action::SaveAs)
+	}
+	
+	fn default_bind(self) -> KeyBind {
+		match self {
+			Action::New => KeyBind::ctrl(Key::N),
+			Action::Open => KeyBind::ctrl(Key::O),
+			Action::Close => KeyBind::ctrl(Key::W),
+			Action::Save => KeyBind::ctrl(Key::S),
+			Action::SaveAs => KeyBind { shift: true, ..KeyBind::ctrl(Key::S) },
+			Action::Undo => KeyBind::ctrl(Key::Z),
+			Action::Redo => KeyBind::ctrl(Key::Y),
+			Action::Copy => KeyBind::ctrl(Key::C),
+			Action::Paste => KeyBind::ctrl(Key::V),
+			Action::AddBone => KeyBind::ctrl(Key::B),
+			Action::DeleteBone => KeyBind::new(Key::Delete),
+			Action::NextView => KeyBind::ctrl(Key::PageDown),
+			Action::PrevView => KeyBind::ctrl(Key::PageUp),
+			Action::NextTab => KeyBind::ctrl(Key::RightArrow),
+			Action::PrevTab => KeyBind::ctrl(Key::LeftArrow),
+			Action::ZoomIn => KeyBind::ctrl(Key::Equal),
+			Action::ZoomOut => KeyBind::ctrl(Key::Minus),
+			Action::ZoomReset => KeyBind::ctrl(Key::Num0),
+			Action::NudgeLeft => KeyBind::new(Key::LeftArrow),
+			Action::NudgeRight => KeyBind::new(Key::RightArrow),
+			Action::NudgeUp => KeyBind::new(Key::UpArrow),
+			Action::NudgeDown => KeyBind::new(Key::DownArrow)
+		}
+	}
+}
+
+// KeyBind
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct KeyBind {
+	pub ctrl: bool,
+	pub shift: bool,
+	pub alt: bool,
+	pub key: Key
+}
+
+impl KeyBind {
+	pub fn new(key: Key) -> Self {
+		Self { ctrl: false, shift: false, alt: false, key }
+	}
+	
+	pub fn ctrl(key: Key) -> Self {
+		Self { ctrl: true, ..Self::new(key) }
+	}
+	
+	// Modifiers must match exactly, so Ctrl+S doesn't also fire on Ctrl+Shift+S.
+	pub fn is_pressed<A>(&self, ui: &Ui<A>) -> bool {
+		let io = ui.io();
+		io.KeyCtrl == self.ctrl
+			&& io.KeyShift == self.shift
+			&& io.KeyAlt == self.alt
+			&& ui.is_key_pressed(self.key)
+	}
+	
+	// Returns the first non-modifier key pressed this frame, for rebinding.
+	pub fn capture<A>(ui: &Ui<A>) -> Option<KeyBind> {
+		let io = ui.io();
+		let key = KEY_NAMES.iter()
+			.map(|(key, _)| *key)
+			.find(|key| ui.is_key_pressed_no_repeat(*key))?;
+		
+		Some(Self {
+			ctrl: io.KeyCtrl,
+			shift: io.KeyShift,
+			alt: io.KeyAlt,
+			key
+		})
+	}
+	
+	pub fn parse(text: &str) -> Option<KeyBind> {
+		let mut parts: Vec<&str> = text.split('+').map(str::trim).collect();
+		let name = parts.pop()?;
+		let key = KEY_NAMES.iter().find(|(_, x)| x.eq_ignore_ascii_case(name))?.0;
+		
+		let mut bind = Self::new(key);
+		for part in parts {
+			match part.to_ascii_lowercase().as_str() {
+				"ctrl" => bind.ctrl = true,
+				"shift" => bind.shift = true,
+				"alt" => bind.alt = true,
+				_ => return None
+			}
+		}
+		Some(bind)
+	}
+}
+
+impl fmt::Display for KeyBind {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		if self.ctrl { write!(f, "Ctrl+")? }
+		if self.shift { write!(f, "Shift+")? }
+		if self.alt { write!(f, "Alt+")? }
+		
+		let name = KEY_NAMES.iter()
+			.find(|(key, _)| *key == self.key)
+			.map_or("?", |(_, name)| name);
+		write!(f, "{}", name)
+	}
+}
+
+// Keymap
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[serde(from = "BTreeMap<String, Option<String>>", into = "BTreeMap<String, Option<String>>")]
+pub struct Keymap {
+	binds: BTreeMap<Action, Option<KeyBind>>
+}
+
+impl Default for Keymap {
+	fn default() -> Self {
+		let binds = Action::ALL.into_iter()
+			.map(|action| (action, Some(action.default_bind())))
+			.collect();
+		Self { binds }
+	}
+}
+
+impl Keymap {
+	pub fn get(&self, action: Action) -> Option<KeyBind> {
+		self.binds.get(&action).copied().flatten()
+	}
+	
+	pub fn set(&mut self, action: Action, bind: Option<KeyBind>) {
+		self.binds.insert(action, bind);
+	}
+	
+	// Shortcut text for menus, empty when unbound.
+	pub fn shortcut(&self, action: Action) -> String {
+		self.get(action).map(|bind| bind.to_string()).unwrap_or_default()
+	}
+	
+	pub fn conflicts(&self, action: Action) -> Vec<Action> {
+		let Some(bind) = self.get(action) else { return Vec::new() };
+		Action::ALL.into_iter()
+			.filter(|other| *other != action && self.get(*other) == Some(bind))
+			.collect()
+	}
+	
+	// Actions triggered this frame. Conflicting bindings all fire.
+	pub fn pressed<A>(&self, ui: &Ui<A>) -> Vec<Action> {
+		Action::ALL.into_iter()
+			.filter(|action| self.get(*action).is_some_and(|bind| bind.is_pressed(ui)))
+			.collect()
+	}
+}
+
+// Unknown actions are dropped and missing ones keep their defaults,
+// so new actions get bound when upgrading from an older config.
+
+impl From<BTreeMap<String, Option<String>>> for Keymap {
+	fn from(map: BTreeMap<String, Option<String>>) -> Self {
+		let mut keymap = Self::default();
+		for (id, text) in map {
+			let Some(action) = Action::from_id(&id) else { continue };
+			match text.as_deref().map(KeyBind::parse) {
+				Some(None) => println!("Invalid shortcut for {}: {}", id, text.unwrap_or_default()),
+				Some(bind) => keymap.set(action, bind),
+				None => keymap.set(action, None)
+			}
+		}
+		keymap
+	}
+}
+
+impl From<Keymap> for BTreeMap<String, Option<String>> {
+	fn from(keymap: Keymap) -> Self {
+		keymap.binds.into_iter()
+			.map(|(action, bind)| (action.id().to_string(), bind.map(|bind| bind.to_string())))
+			.collect()
+	}
+}
+
+// Key names
+
+const KEY_NAMES: &[(Key, &str)] = &[
+	(Key::A, "A"), (Key::B, "B"), (Key::C, "C"), (Key::D, "D"), (Key::E, "E"),
+	(Key::F, "F"), (Key::G, "G"), (Key::H, "H"), (Key::I, "I"), (Key::J, "J"),
+	(Key::K, "K"), (Key::L, "L"), (Key::M, "M"), (Key::N, "N"), (Key::O, "O"),
+	(Key::P, "P"), (Key::Q, "Q"), (Key::R, "R"), (Key::S, "S"), (Key::T, "T"),
+	(Key::U, "U"), (Key::V, "V"), (Key::W, "W"), (Key::X, "X"), (Key::Y, "Y"),
+	(Key::Z, "Z"),
+	(Key::Num0, "0"), (Key::Num1, "1"), (Key::Num2, "2"), (Key::Num3, "3"), (Key::Num4, "4"),
+	(Key::Num5, "5"), (Key::Num6, "6"), (Key::Num7, "7"), (Key::Num8, "8"), (Key::Num9, "9"),
+	(Key::F1, "F1"), (Key::F2, "F2"), (Key::F3, "F3"), (Key::F4, "F4"),
+	(Key::F5, "F5"), (Key::F6, "F6"), (Key::F7, "F7"), (Key::F8, "F8"),
+	(Key::F9, "F9"), (Key::F10, "F10"), (Key::F11, "F11"), (Key::F12, "F12"),
+	(Key::LeftArrow, "Left"), (Key::RightArrow, "Right"),
+	(Key::UpArrow, "Up"), (Key::DownArrow, "Down"),
+	(Key::PageUp, "PageUp"), (Key::PageDown, "PageDown"),
+	(Key::Home, "Home"), (Key::End, "End"),
+	(Key::Insert, "Insert"), (Key::Delete, "Delete"),
+	(Key::Backspace, "Backspace"), (Key::Space, "Space"),
+	(Key::Enter, "Enter"), (Key::Tab, "Tab"),
+	(Key::Minus, "Minus"), (Key::Equal, "Equal"),
+	(Key::Comma, "Comma"), (Key::Period, "Period"), (Key::Slash, "Slash"),
+	(Key::LeftBracket, "LeftBracket"), (Key::RightBracket, "RightBracket"),
+	(Key::KeypadAdd, "KeypadAdd"), (Key::KeypadSubtract, "KeypadSubtract")
+];
\ No newline at end of file
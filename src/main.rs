@@ -2,9 +2,25 @@ mod app;
 mod gui;
 mod schema;
 mod config;
+mod cli;
+mod keys;
+mod render;
 
 use app::App;
+use cli::Command;
 
 fn main() {
-	App::run();
+	match Command::from_env() {
+		Command::Launch(args) => App::run(args),
+		Command::Diff(args) => std::process::exit(args.run()),
+		Command::Merge(args) => {
+			let code = args.run();
+			// The written result holds the automatic part, conflicts are then picked in the editor.
+			match code == 1 && args.gui {
+				true => App::run(args.launch_args()),
+				false => std::process::exit(code)
+			}
+		},
+		Command::Render(args) => std::process::exit(args.run())
+	}
 }
\ No newline at end of file
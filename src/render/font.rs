@@ -0,0 +1,138 @@
+﻿use easy_imgui::image::RgbaImage;
+
+// Bitmap font
+//
+// 5x7 glyphs for printable ASCII, one byte per column with the top row in the lowest bit.
+// Other characters are drawn as a box.
+
+pub const GLYPH_WIDTH: u32 = 5;
+pub const GLYPH_HEIGHT: u32 = 7;
+// Gap between characters, in font pixels.
+const SPACING: u32 = 1;
+
+const UNKNOWN: [u8; 5] = [ 0x7F, 0x41, 0x41, 0x41, 0x7F ];
+
+const GLYPHS: [[u8; 5]; 95] = [
+	[ 0x00, 0x00, 0x00, 0x00, 0x00 ],
+	[ 0x00, 0x00, 0x5F, 0x00, 0x00 ],
+	[ 0x00, 0x07, 0x00, 0x07, 0x00 ],
+	[ 0x14, 0x7F, 0x14, 0x7F, 0x14 ],
+	[ 0x24, 0x2A, 0x7F, 0x2A, 0x12 ],
+	[ 0x23, 0x13, 0x08, 0x64, 0x62 ],
+	[ 0x36, 0x49, 0x55, 0x22, 0x50 ],
+	[ 0x00, 0x05, 0x03, 0x00, 0x00 ],
+	[ 0x00, 0x1C, 0x22, 0x41, 0x00 ],
+	[ 0x00, 0x41, 0x22, 0x1C, 0x00 ],
+	[ 0x14, 0x08, 0x3E, 0x08, 0x14 ],
+	[ 0x08, 0x08, 0x3E, 0x08, 0x08 ],
+	[ 0x00, 0x50, 0x30, 0x00, 0x00 ],
+	[ 0x08, 0x08, 0x08, 0x08, 0x08 ],
+	[ 0x00, 0x60, 0x60, 0x00, 0x00 ],
+	[ 0x20, 0x10, 0x08, 0x04, 0x02 ],
+	[ 0x3E, 0x51, 0x49, 0x45, 0x3E ],
+	[ 0x00, 0x42, 0x7F, 0x40, 0x00 ],
+	[ 0x42, 0x61, 0x51, 0x49, 0x46 ],
+	[ 0x21, 0x41, 0x45, 0x4B, 0x31 ],
+	[ 0x18, 0x14, 0x12, 0x7F, 0x10 ],
+	[ 0x27, 0x45, 0x45, 0x45, 0x39 ],
+	[ 0x3C, 0x4A, 0x49, 0x49, 0x30 ],
+	[ 0x01, 0x71, 0x09, 0x05, 0x03 ],
+	[ 0x36, 0x49, 0x49, 0x49, 0x36 ],
+	[ 0x06, 0x49, 0x49, 0x29, 0x1E ],
+	[ 0x00, 0x36, 0x36, 0x00, 0x00 ],
+	[ 0x00, 0x56, 0x36, 0x00, 0x00 ],
+	[ 0x08, 0x14, 0x22, 0x41, 0x00 ],
+	[ 0x14, 0x14, 0x14, 0x14, 0x14 ],
+	[ 0x00, 0x41, 0x22, 0x14, 0x08 ],
+	[ 0x02, 0x01, 0x51, 0x09, 0x06 ],
+	[ 0x32, 0x49, 0x79, 0x41, 0x3E ],
+	[ 0x7E, 0x11, 0x11, 0x11, 0x7E ],
+	[ 0x7F, 0x49, 0x49, 0x49, 0x36 ],
+	[ 0x3E, 0x41, 0x41, 0x41, 0x22 ],
+	[ 0x7F, 0x41, 0x41, 0x22, 0x1C ],
+	[ 0x7F, 0x49, 0x49, 0x49, 0x41 ],
+	[ 0x7F, 0x09, 0x09, 0x01, 0x01 ],
+	[ 0x3E, 0x41, 0x41, 0x51, 0x32 ],
+	[ 0x7F, 0x08, 0x08, 0x08, 0x7F ],
+	[ 0x00, 0x41, 0x7F, 0x41, 0x00 ],
+	[ 0x20, 0x40, 0x41, 0x3F, 0x01 ],
+	[ 0x7F, 0x08, 0x14, 0x22, 0x41 ],
+	[ 0x7F, 0x40, 0x40, 0x40, 0x40 ],
+	[ 0x7F, 0x02, 0x04, 0x02, 0x7F ],
+	[ 0x7F, 0x04, 0x08, 0x10, 0x7F ],
+	[ 0x3E, 0x41, 0x41, 0x41, 0x3E ],
+	[ 0x7F, 0x09, 0x09, 0x09, 0x06 ],
+	[ 0x3E, 0x41, 0x51, 0x21, 0x5E ],
+	[ 0x7F, 0x09, 0x19, 0x29, 0x46 ],
+	[ 0x46, 0x49, 0x49, 0x49, 0x31 ],
+	[ 0x01, 0x01, 0x7F, 0x01, 0x01 ],
+	[ 0x3F, 0x40, 0x40, 0x40, 0x3F ],
+	[ 0x1F, 0x20, 0x40, 0x20, 0x1F ],
+	[ 0x7F, 0x20, 0x18, 0x20, 0x7F ],
+	[ 0x63, 0x14, 0x08, 0x14, 0x63 ],
+	[ 0x03, 0x04, 0x78, 0x04, 0x03 ],
+	[ 0x61, 0x51, 0x49, 0x45, 0x43 ],
+	[ 0x00, 0x7F, 0x41, 0x41, 0x00 ],
+	[ 0x02, 0x04, 0x08, 0x10, 0x20 ],
+	[ 0x00, 0x41, 0x41, 0x7F, 0x00 ],
+	[ 0x04, 0x02, 0x01, 0x02, 0x04 ],
+	[ 0x40, 0x40, 0x40, 0x40, 0x40 ],
+	[ 0x00, 0x01, 0x02, 0x04, 0x00 ],
+	[ 0x20, 0x54, 0x54, 0x54, 0x78 ],
+	[ 0x7F, 0x48, 0x44, 0x44, 0x38 ],
+	[ 0x38, 0x44, 0x44, 0x44, 0x20 ],
+	[ 0x38, 0x44, 0x44, 0x48, 0x7F ],
+	[ 0x38, 0x54, 0x54, 0x54, 0x18 ],
+	[ 0x08, 0x7E, 0x09, 0x01, 0x02 ],
+	[ 0x0C, 0x52, 0x52, 0x52, 0x3E ],
+	[ 0x7F, 0x08, 0x04, 0x04, 0x78 ],
+	[ 0x00, 0x44, 0x7D, 0x40, 0x00 ],
+	[ 0x20, 0x40, 0x44, 0x3D, 0x00 ],
+	[ 0x7F, 0x10, 0x28, 0x44, 0x00 ],
+	[ 0x00, 0x41, 0x7F, 0x40, 0x00 ],
+	[ 0x7C, 0x04, 0x18, 0x04, 0x78 ],
+	[ 0x7C, 0x08, 0x04, 0x04, 0x78 ],
+	[ 0x38, 0x44, 0x44, 0x44, 0x38 ],
+	[ 0x7C, 0x14, 0x14, 0x14, 0x08 ],
+	[ 0x08, 0x14, 0x14, 0x18, 0x7C ],
+	[ 0x7C, 0x08, 0x04, 0x04, 0x08 ],
+	[ 0x48, 0x54, 0x54, 0x54, 0x20 ],
+	[ 0x04, 0x3F, 0x44, 0x40, 0x20 ],
+	[ 0x3C, 0x40, 0x40, 0x20, 0x7C ],
+	[ 0x1C, 0x20, 0x40, 0x20, 0x1C ],
+	[ 0x3C, 0x40, 0x30, 0x40, 0x3C ],
+	[ 0x44, 0x28, 0x10, 0x28, 0x44 ],
+	[ 0x0C, 0x50, 0x50, 0x50, 0x3C ],
+	[ 0x44, 0x64, 0x54, 0x4C, 0x44 ],
+	[ 0x00, 0x08, 0x36, 0x41, 0x00 ],
+	[ 0x00, 0x00, 0x7F, 0x00, 0x00 ],
+	[ 0x00, 0x41, 0x36, 0x08, 0x00 ],
+	[ 0x02, 0x01, 0x02, 0x04, 0x02 ]
+];
+
+// Size of `text` in image pixels, drawn at `scale`.
+pub fn measure(text: &str, scale: u32) -> [u32; 2] {
+	let count = text.chars().count() as u32;
+	let width = (count * (GLYPH_WIDTH + SPACING)).saturating_sub(SPACING);
+	[ width * scale, GLYPH_HEIGHT * scale ]
+}
+
+// Draws `text` with its top left corner at `pos`, each font pixel `scale` image pixels wide.
+pub fn draw_text(img: &mut RgbaImage, pos: [i32; 2], text: &str, scale: u32, color: [f32; 4]) {
+	let step = ((GLYPH_WIDTH + SPACING) * scale) as i32;
+	for (i, c) in text.chars().enumerate() {
+		let glyph = match c {
+			' '..='~' => &GLYPHS[c as usize - 32],
+			_ => &UNKNOWN
+		};
+		let left = pos[0] + i as i32 * step;
+		for (col, bits) in glyph.iter().enumerate() {
+			for row in 0..GLYPH_HEIGHT {
+				if bits >> row & 1 == 0 { continue }
+				let x = left + (col as u32 * scale) as i32;
+				let y = pos[1] + (row * scale) as i32;
+				super::fill_rect(img, [ x, y ], [ x + scale as i32, y + scale as i32 ], color);
+			}
+		}
+	}
+}
\ No newline at end of file
@@ -0,0 +1,167 @@
+﻿use crate::{config::Configuration, schema::data::SchemaData};
+use std::path::PathBuf;
+use serde_json::{json, Value};
+
+// HTML preview
+//
+// A single page with every view of a schema, for trying out a layout in a browser.
+// Images are embedded so the file can be shared on its own, and bones are placed by their
+// normalized coordinates over whichever image tab is open.
+
+const TEMPLATE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{title}</title>
+<style>
+	body { margin: 0; font-family: sans-serif; font-size: 14px; background: #1e1e22; color: #ddd; }
+	header { padding: 8px 12px; background: #2a2a30; }
+	h1 { margin: 0 0 8px; font-size: 16px; }
+	.tabs { display: flex; flex-wrap: wrap; gap: 4px; margin-bottom: 8px; }
+	.tabs button { padding: 4px 10px; border: 1px solid #555; border-radius: 3px; background: #333; color: inherit; cursor: pointer; }
+	.tabs button.active { background: #5a5a66; border-color: #888; }
+	main { display: flex; gap: 12px; padding: 12px; align-items: flex-start; }
+	.canvas { position: relative; display: inline-block; line-height: 0; border: 1px solid #555; }
+	.canvas img { max-width: calc(100vw - 320px); max-height: calc(100vh - 140px); }
+	.blank { height: calc(100vh - 140px); max-width: calc(100vw - 320px); background: repeating-conic-gradient(#666 0 25%, #4d4d4d 0 50%) 0 0 / 32px 32px; }
+	.notice { position: absolute; top: 8px; left: 8px; line-height: normal; color: #ffb366; }
+	.marker { position: absolute; width: {size}px; height: {size}px; margin: -{radius}px 0 0 -{radius}px; padding: 0; box-sizing: border-box;
+		border: 1.5px solid #000; border-radius: 50%; background: {color}; cursor: pointer; }
+	.marker.selected { background: {selected}; border-width: 2.5px; }
+	aside { min-width: 250px; line-height: 1.6; }
+	aside .label { font-size: 16px; font-weight: bold; }
+	aside .empty { color: #888; }
+</style>
+</head>
+<body>
+<header>
+	<h1>{title}</h1>
+	<div class="tabs" id="views"></div>
+	<div class="tabs" id="images"></div>
+</header>
+<main>
+	<div class="canvas" id="canvas"></div>
+	<aside id="info"></aside>
+</main>
+<script>
+const schema = {data};
+let view = 0, image = 0, bone = null;
+
+function el(tag, props, children) {
+	const node = Object.assign(document.createElement(tag), props);
+	(children || []).forEach(child => node.append(child));
+	return node;
+}
+
+function tabs(id, items, active, select) {
+	document.getElementById(id).replaceChildren(...items.map((text, i) =>
+		el("button", { textContent: text, className: i === active ? "active" : "", onclick: () => select(i) })));
+}
+
+function draw() {
+	const current = schema.views[view];
+	tabs("views", schema.views.map(x => x.label), view, i => { view = i; image = 0; bone = null; draw(); });
+	tabs("images", current.images.map(x => x.file), image, i => { image = i; draw(); });
+	
+	const canvas = document.getElementById("canvas");
+	const img = current.images[image];
+	const content = [];
+	if (img && img.src) {
+		content.push(el("img", { src: img.src }));
+	} else {
+		const blank = el("div", { className: "blank" });
+		blank.style.aspectRatio = schema.blank[0] + " / " + schema.blank[1];
+		content.push(blank, el("div", { className: "notice", textContent: img ? "Image not found: " + img.file : "No images in this view." }));
+	}
+	current.bones.forEach((b, i) => {
+		const marker = el("button", { className: "marker" + (i === bone ? " selected" : ""), title: b.label });
+		marker.style.left = (b.x * 100) + "%";
+		marker.style.top = (b.y * 100) + "%";
+		marker.onclick = () => { bone = i; draw(); };
+		content.push(marker);
+	});
+	canvas.replaceChildren(...content);
+	
+	const info = document.getElementById("info");
+	const b = current.bones[bone];
+	info.replaceChildren(...(b ? [
+		el("div", { className: "label", textContent: b.label }),
+		el("div", { textContent: "Name: " + b.name }),
+		el("div", { textContent: "X: " + b.x.toFixed(4) + ", Y: " + b.y.toFixed(4) })
+	] : [ el("div", { className: "empty", textContent: "Click a bone to see its name and label." }) ]));
+}
+
+draw();
+</script>
+</body>
+</html>
+"#;
+
+pub fn export(data: &SchemaData, title: &str, cfg: &Configuration) -> String {
+	let lang = cfg.ui.language.as_deref();
+	let path = |file: &str| cfg.image_root().map(|root| PathBuf::from(root).join(file));
+	
+	let views: Vec<Value> = data.views.iter().map(|view| json!({
+		"name": view.name,
+		"label": view.display_name(lang),
+		"images": view.images.iter().map(|img| json!({
+			"file": img.file,
+			"src": path(&img.file).and_then(|path| super::data_uri(&path))
+		})).collect::<Vec<_>>(),
+		"bones": view.bones.iter().map(|bone| json!({
+			"name": bone.name,
+			"label": bone.display_label(lang),
+			"x": bone.x,
+			"y": bone.y
+		})).collect::<Vec<_>>()
+	})).collect();
+	
+	let data = json!({ "views": views, "blank": cfg.blank_aspect });
+	// Keeps names containing `</script>` from ending the script early.
+	let data = data.to_string().replace("</", "<\\/");
+	
+	let radius = cfg.ui.marker_radius;
+	fill(TEMPLATE, &[
+		("{title}", &escape(title)),
+		("{radius}", &radius.to_string()),
+		("{size}", &(radius * 2.0).to_string()),
+		("{color}", &css_color(cfg.ui.marker_color)),
+		("{selected}", &css_color(cfg.ui.marker_selected_color)),
+		("{data}", &data)
+	])
+}
+
+// Substitutes placeholders in a single pass, so a title or bone name that happens to
+// contain one is left as written instead of being filled in by a later substitution.
+fn fill(template: &str, values: &[(&str, &str)]) -> String {
+	let mut out = String::with_capacity(template.len());
+	let mut rest = template;
+	while let Some(start) = rest.find('{') {
+		out.push_str(&rest[..start]);
+		rest = &rest[start..];
+		match values.iter().find(|(key, _)| rest.starts_with(key)) {
+			Some((key, value)) => {
+				out.push_str(value);
+				rest = &rest[key.len()..];
+			},
+			None => {
+				out.push('{');
+				rest = &rest[1..];
+			}
+		}
+	}
+	out.push_str(rest);
+	out
+}
+
+fn escape(text: &str) -> String {
+	text.replace('&', "&amp;")
+		.replace('<', "&lt;")
+		.replace('>', "&gt;")
+		.replace('"', "&quot;")
+}
+
+fn css_color([ r, g, b, a ]: [f32; 4]) -> String {
+	let channel = |x: f32| (x.clamp(0.0, 1.0) * 255.0).round() as u8;
+	format!("rgba({}, {}, {}, {})", channel(r), channel(g), channel(b), a.clamp(0.0, 1.0))
+}
\ No newline at end of file
@@ -0,0 +1,306 @@
+﻿use crate::{
+	config::Configuration,
+	schema::{
+		data::{SchemaBone, SchemaView},
+		project::SchemaProject,
+		skeleton::SkeletonBone
+	}
+};
+use std::path::{Path, PathBuf};
+use easy_imgui::image::{self, imageops::{self, FilterType}, RgbaImage};
+
+pub mod html;
+pub mod svg;
+
+mod font;
+
+// Preview rendering
+//
+// Draws a view's image and its bones on the CPU, so previews can be made without a window.
+// Markers are sized as they'd appear with the image fit to a 720 pixel tall canvas.
+
+const REFERENCE_HEIGHT: f32 = 720.0;
+// Longest side of blank canvases, which have no size of their own.
+const BLANK_SIZE: u32 = 1024;
+
+const OUTLINE_COLOR: [f32; 4] = [ 0.0, 0.0, 0.0, 1.0 ];
+const LINE_COLOR: [f32; 4] = [ 1.0, 1.0, 1.0, 0.8 ];
+const LABEL_BACKGROUND: [f32; 4] = [ 0.0, 0.0, 0.0, 0.65 ];
+const LABEL_COLOR: [f32; 4] = [ 1.0, 1.0, 1.0, 1.0 ];
+
+pub struct RenderOptions {
+	// Image drawn under the bones, with its visible overlay layers and their opacity.
+	pub image: Option<PathBuf>,
+	pub layers: Vec<(PathBuf, f32)>,
+	// Aspect ratio of the canvas when there's no image.
+	pub blank: [u32; 2],
+	pub radius: f32,
+	pub color: [f32; 4],
+	pub labels: bool,
+	pub language: Option<String>,
+	// Lines are drawn from each bone to its closest ancestor in the view.
+	pub hierarchy: Option<Vec<SkeletonBone>>,
+	// Shades the area each marker responds to clicks in.
+	pub hit_map: bool
+}
+
+impl RenderOptions {
+	// Editor settings for `view`, showing the `tab` image or else the first.
+	pub fn new(cfg: &Configuration, project: &SchemaProject, view: &SchemaView, tab: Option<&str>) -> Self {
+		let file = tab
+			.filter(|tab| view.images.iter().any(|x| x.file == *tab))
+			.or(view.images.first().map(|x| x.file.as_str()));
+		let path = |file: &str| cfg.image_root().map(|root| PathBuf::from(root).join(file));
+		
+		let layers = file.map_or_else(Vec::new, |file| project.layers(&view.name, file).iter()
+			.filter(|layer| layer.visible)
+			.filter_map(|layer| Some((path(&layer.file)?, layer.opacity)))
+			.collect());
+		
+		Self {
+			image: file.and_then(path),
+			layers,
+			blank: cfg.blank_aspect,
+			radius: cfg.ui.marker_radius,
+			color: cfg.ui.marker_color,
+			labels: false,
+			language: cfg.ui.language.clone(),
+			hierarchy: None,
+			hit_map: false
+		}
+	}
+}
+
+pub fn render(view: &SchemaView, options: &RenderOptions) -> Result<RgbaImage, String> {
+	let mut img = match &options.image {
+		Some(path) => open_image(path)?,
+		None => checkerboard(blank_size(options.blank))
+	};
+	let (width, height) = img.dimensions();
+	
+	// Layers that fail to load are left out, as in the editor.
+	for (path, opacity) in &options.layers {
+		let Ok(layer) = open_image(path) else { continue };
+		let layer = imageops::resize(&layer, width, height, FilterType::Triangle);
+		for (dst, src) in img.pixels_mut().zip(layer.pixels()) {
+			let color = src.0.map(|x| x as f32 / 255.0);
+			blend(dst, [ color[0], color[1], color[2], color[3] * opacity ], 1.0);
+		}
+	}
+	
+	let scale = (height as f32 / REFERENCE_HEIGHT).max(1.0);
+	let radius = options.radius * scale;
+	let point = |bone: &SchemaBone| [ bone.x * width as f32, bone.y * height as f32 ];
+	
+	if options.hit_map {
+		for (i, bone) in view.bones.iter().enumerate() {
+			let [ x, y ] = point(bone);
+			let [ r, g, b ] = hue(i);
+			let (min, max) = ([ x - radius, y - radius ], [ x + radius, y + radius ]);
+			fill_rect(&mut img, min.map(|x| x.round() as i32), max.map(|x| x.round() as i32), [ r, g, b, 0.4 ]);
+		}
+	}
+	
+	if let Some(skeleton) = &options.hierarchy {
+		for (parent, child) in hierarchy_lines(view, skeleton) {
+			let (from, to) = (point(&view.bones[parent]), point(&view.bones[child]));
+			draw_line(&mut img, from, to, 3.0 * scale, OUTLINE_COLOR);
+			draw_line(&mut img, from, to, 1.5 * scale, LINE_COLOR);
+		}
+	}
+	
+	for bone in &view.bones {
+		let center = point(bone);
+		fill_circle(&mut img, center, radius, options.color);
+		stroke_circle(&mut img, center, radius, 1.5 * scale, OUTLINE_COLOR);
+	}
+	
+	if options.labels {
+		let text_scale = (scale * 2.0).round() as u32;
+		let pad = text_scale as i32 * 2;
+		for bone in &view.bones {
+			let [ x, y ] = point(bone);
+			// The font only covers ASCII, so translations it can't draw fall back to the label.
+			let text = match bone.display_label(options.language.as_deref()) {
+				text if text.is_ascii() => text,
+				_ => &bone.label
+			};
+			let [ text_width, text_height ] = font::measure(text, text_scale).map(|x| x as i32);
+			
+			// Labels go right of the marker, or left when they'd run off the image.
+			let gap = (radius + 4.0 * scale) as i32 + pad;
+			let mut left = x as i32 + gap;
+			if left + text_width + pad > width as i32 {
+				left = x as i32 - gap - text_width;
+			}
+			let top = y as i32 - text_height / 2;
+			
+			fill_rect(&mut img, [ left - pad, top - pad ], [ left + text_width + pad, top + text_height + pad ], LABEL_BACKGROUND);
+			font::draw_text(&mut img, [ left, top ], text, text_scale, LABEL_COLOR);
+		}
+	}
+	
+	Ok(img)
+}
+
+fn open_image(path: &Path) -> Result<RgbaImage, String> {
+	image::open(path)
+		.map(|img| img.into_rgba8())
+		.map_err(|err| format!("Failed to load {}: {}", path.display(), err))
+}
+
+fn blank_size([ width, height ]: [u32; 2]) -> [u32; 2] {
+	let (width, height) = (width.max(1) as f32, height.max(1) as f32);
+	let fit = BLANK_SIZE as f32 / width.max(height);
+	[ (width * fit).round().max(1.0) as u32, (height * fit).round().max(1.0) as u32 ]
+}
+
+// Same pattern as the editor's blank canvas.
+fn checkerboard([ width, height ]: [u32; 2]) -> RgbaImage {
+	const CELLS: u32 = 16;
+	let cell = (width.min(height) / CELLS).max(1);
+	RgbaImage::from_fn(width, height, |x, y| {
+		let value = match (x / cell + y / cell) % 2 {
+			0 => 102,
+			_ => 77
+		};
+		image::Rgba([ value, value, value, 255 ])
+	})
+}
+
+// Pairs of bone indices in the view, parent first.
+fn hierarchy_lines(view: &SchemaView, skeleton: &[SkeletonBone]) -> Vec<(usize, usize)> {
+	let find = |name: &str| view.bones.iter().position(|bone| bone.name == name);
+	
+	let mut lines = Vec::new();
+	for (i, bone) in view.bones.iter().enumerate() {
+		let Some(mut index) = skeleton.iter().position(|x| x.name == bone.name) else { continue };
+		
+		// Ancestors missing from the view are skipped over, the step limit guards against cycles.
+		for _ in 0..skeleton.len() {
+			let Some(parent) = skeleton[index].parent else { break };
+			if let Some(j) = find(&skeleton[parent].name) {
+				lines.push((j, i));
+				break;
+			}
+			index = parent;
+		}
+	}
+	lines
+}
+
+// Evenly spread colors, so neighbouring bones are told apart.
+fn hue(i: usize) -> [f32; 3] {
+	let h = (i as f32 * 0.618_034).fract() * 6.0;
+	let x = 1.0 - (h % 2.0 - 1.0).abs();
+	match h as u32 {
+		0 => [ 1.0, x, 0.0 ],
+		1 => [ x, 1.0, 0.0 ],
+		2 => [ 0.0, 1.0, x ],
+		3 => [ 0.0, x, 1.0 ],
+		4 => [ x, 0.0, 1.0 ],
+		_ => [ 1.0, 0.0, x ]
+	}
+}
+
+// Embedding
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+// Image file as a `data:` URI, for pages that carry their images inline.
+fn data_uri(path: &Path) -> Option<String> {
+	let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+	let mime = match extension.as_str() {
+		"png" => "image/png",
+		"jpg" | "jpeg" => "image/jpeg",
+		"gif" => "image/gif",
+		"webp" => "image/webp",
+		"bmp" => "image/bmp",
+		_ => return None
+	};
+	let bytes = std::fs::read(path).ok()?;
+	Some(format!("data:{};base64,{}", mime, base64(&bytes)))
+}
+
+fn base64(bytes: &[u8]) -> String {
+	let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+	for chunk in bytes.chunks(3) {
+		let n = chunk.iter().enumerate().fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - i * 8));
+		for i in 0..4 {
+			match i <= chunk.len() {
+				true => out.push(BASE64[(n >> (18 - i * 6) & 63) as usize] as char),
+				false => out.push('=')
+			}
+		}
+	}
+	out
+}
+
+// Drawing
+
+fn blend(pixel: &mut image::Rgba<u8>, color: [f32; 4], coverage: f32) {
+	let alpha = color[3] * coverage.clamp(0.0, 1.0);
+	if alpha <= 0.0 { return }
+	
+	let dst_alpha = pixel[3] as f32 / 255.0;
+	let out_alpha = alpha + dst_alpha * (1.0 - alpha);
+	for c in 0..3 {
+		let dst = pixel[c] as f32 / 255.0;
+		let value = (color[c] * alpha + dst * dst_alpha * (1.0 - alpha)) / out_alpha;
+		pixel[c] = (value * 255.0).round() as u8;
+	}
+	pixel[3] = (out_alpha * 255.0).round() as u8;
+}
+
+// Blends `color` over the pixels around `min` to `max`, by the coverage of each pixel's center.
+fn draw_shape(img: &mut RgbaImage, min: [f32; 2], max: [f32; 2], color: [f32; 4], coverage: impl Fn(f32, f32) -> f32) {
+	let (width, height) = img.dimensions();
+	let x0 = (min[0].floor() - 1.0).max(0.0) as u32;
+	let y0 = (min[1].floor() - 1.0).max(0.0) as u32;
+	let x1 = ((max[0].ceil() + 1.0).max(0.0) as u32).min(width);
+	let y1 = ((max[1].ceil() + 1.0).max(0.0) as u32).min(height);
+	
+	for y in y0..y1 {
+		for x in x0..x1 {
+			let amount = coverage(x as f32 + 0.5, y as f32 + 0.5);
+			blend(img.get_pixel_mut(x, y), color, amount);
+		}
+	}
+}
+
+fn fill_rect(img: &mut RgbaImage, min: [i32; 2], max: [i32; 2], color: [f32; 4]) {
+	let (width, height) = img.dimensions();
+	for y in min[1].max(0)..max[1].min(height as i32) {
+		for x in min[0].max(0)..max[0].min(width as i32) {
+			blend(img.get_pixel_mut(x as u32, y as u32), color, 1.0);
+		}
+	}
+}
+
+fn fill_circle(img: &mut RgbaImage, [ cx, cy ]: [f32; 2], radius: f32, color: [f32; 4]) {
+	let (min, max) = ([ cx - radius, cy - radius ], [ cx + radius, cy + radius ]);
+	draw_shape(img, min, max, color, |x, y| radius - (x - cx).hypot(y - cy) + 0.5);
+}
+
+fn stroke_circle(img: &mut RgbaImage, [ cx, cy ]: [f32; 2], radius: f32, thickness: f32, color: [f32; 4]) {
+	let outer = radius + thickness;
+	let (min, max) = ([ cx - outer, cy - outer ], [ cx + outer, cy + outer ]);
+	draw_shape(img, min, max, color, |x, y| thickness * 0.5 - ((x - cx).hypot(y - cy) - radius).abs() + 0.5);
+}
+
+fn draw_line(img: &mut RgbaImage, from: [f32; 2], to: [f32; 2], thickness: f32, color: [f32; 4]) {
+	let delta = [ to[0] - from[0], to[1] - from[1] ];
+	let length = delta[0] * delta[0] + delta[1] * delta[1];
+	let pad = thickness;
+	let min = [ from[0].min(to[0]) - pad, from[1].min(to[1]) - pad ];
+	let max = [ from[0].max(to[0]) + pad, from[1].max(to[1]) + pad ];
+	
+	draw_shape(img, min, max, color, |x, y| {
+		let t = match length > 0.0 {
+			true => (((x - from[0]) * delta[0] + (y - from[1]) * delta[1]) / length).clamp(0.0, 1.0),
+			false => 0.0
+		};
+		let distance = (x - from[0] - delta[0] * t).hypot(y - from[1] - delta[1] * t);
+		thickness * 0.5 - distance + 0.5
+	});
+}
\ No newline at end of file
@@ -0,0 +1,362 @@
+﻿use crate::{config::Configuration, schema::data::SchemaView};
+use std::path::{Path, PathBuf};
+use easy_imgui::image;
+use quick_xml::{events::{BytesStart, Event}, Reader};
+
+// SVG
+//
+// A view is written as an image layer and a bone layer, for adjusting the layout in vector editors.
+// Each bone is a circle with `data-name` and `data-label` attributes, and `data-occurrence` counting
+// the bones before it with the same name. Ids are the bone name, numbered where it repeats or is empty.
+// Reading takes the circle centers back by name and occurrence, or by id for circles without them,
+// through any transforms an editor added on the way.
+
+pub fn export(view: &SchemaView, cfg: &Configuration, tab: Option<&str>, embed: bool) -> Result<String, String> {
+	let file = tab
+		.filter(|tab| view.images.iter().any(|x| x.file == *tab))
+		.or(view.images.first().map(|x| x.file.as_str()));
+	
+	let (image, [ width, height ]) = match file {
+		Some(file) => {
+			let Some(root) = cfg.image_root() else {
+				return Err("No image path is set.".to_string());
+			};
+			let path = PathBuf::from(root).join(file);
+			let size = image::image_dimensions(&path)
+				.map_err(|err| format!("Failed to load {}: {}", path.display(), err))?;
+			let href = match embed {
+				true => super::data_uri(&path).ok_or_else(|| format!("Unsupported image format: {}", path.display()))?,
+				false => link(&path)
+			};
+			(Some(href), [ size.0, size.1 ])
+		},
+		None => (None, super::blank_size(cfg.blank_aspect))
+	};
+	
+	let scale = (height as f32 / super::REFERENCE_HEIGHT).max(1.0);
+	let radius = cfg.ui.marker_radius * scale;
+	let [ r, g, b, a ] = cfg.ui.marker_color.map(|x| x.clamp(0.0, 1.0));
+	let fill = format!("fill:#{:02x}{:02x}{:02x};fill-opacity:{}", channel(r), channel(g), channel(b), a);
+	
+	let mut out = String::new();
+	out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+	out.push_str(&format!(
+		"<svg xmlns=\"http://www.w3.org/2000/svg\" xmlns:xlink=\"http://www.w3.org/1999/xlink\" \
+		xmlns:inkscape=\"http://www.inkscape.org/namespaces/inkscape\" \
+		xmlns:sodipodi=\"http://sodipodi.sourceforge.net/DTD/sodipodi-0.dtd\" \
+		width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\" data-view=\"{2}\">\n",
+		width, height, escape(&view.name)
+	));
+	
+	// The image layer is locked, so dragging a bone doesn't pick up the image behind it.
+	out.push_str("\t<g id=\"image\" inkscape:groupmode=\"layer\" inkscape:label=\"Image\" sodipodi:insensitive=\"true\">\n");
+	match &image {
+		Some(href) => out.push_str(&format!(
+			"\t\t<image x=\"0\" y=\"0\" width=\"{}\" height=\"{}\" preserveAspectRatio=\"none\" xlink:href=\"{}\"/>\n",
+			width, height, escape(href)
+		)),
+		None => out.push_str(&format!(
+			"\t\t<rect x=\"0\" y=\"0\" width=\"{}\" height=\"{}\" style=\"fill:#5a5a5a\"/>\n",
+			width, height
+		))
+	}
+	out.push_str("\t</g>\n");
+	
+	out.push_str("\t<g id=\"bones\" inkscape:groupmode=\"layer\" inkscape:label=\"Bones\">\n");
+	for (i, (bone, id)) in view.bones.iter().zip(circle_ids(view)).enumerate() {
+		let (name, label) = (escape(&bone.name), escape(&bone.label));
+		out.push_str(&format!(
+			"\t\t<circle id=\"{0}\" data-name=\"{1}\" data-occurrence=\"{2}\" data-label=\"{3}\" inkscape:label=\"{3}\" \
+			cx=\"{4:.3}\" cy=\"{5:.3}\" r=\"{6:.3}\" style=\"{7};stroke:#000000;stroke-width:{8:.3}\"/>\n",
+			escape(&id), name, occurrence(view, i), label,
+			bone.x * width as f32, bone.y * height as f32, radius, fill, 1.5 * scale
+		));
+	}
+	out.push_str("\t</g>\n");
+	out.push_str("</svg>\n");
+	Ok(out)
+}
+
+// Ids for the circles of each bone, in order. Bones keep their name as long as it's unique,
+// empty names become `bone` and repeats are numbered from 2.
+fn circle_ids(view: &SchemaView) -> Vec<String> {
+	let mut ids: Vec<String> = Vec::new();
+	for bone in &view.bones {
+		let base = match bone.name.is_empty() {
+			true => "bone",
+			false => bone.name.as_str()
+		};
+		let mut id = base.to_string();
+		let mut n = 2;
+		while ids.contains(&id) {
+			id = format!("{}-{}", base, n);
+			n += 1;
+		}
+		ids.push(id);
+	}
+	ids
+}
+
+// Bones before the one at `index` with the same name.
+fn occurrence(view: &SchemaView, index: usize) -> usize {
+	let name = &view.bones[index].name;
+	view.bones[..index].iter().filter(|bone| bone.name == *name).count()
+}
+
+fn link(path: &Path) -> String {
+	// Backslashes aren't path separators in URLs, editors on every platform read forward slashes.
+	path.to_string_lossy().replace('\\', "/")
+}
+
+fn escape(text: &str) -> String {
+	text.replace('&', "&amp;")
+		.replace('<', "&lt;")
+		.replace('>', "&gt;")
+		.replace('"', "&quot;")
+}
+
+fn channel(x: f32) -> u8 {
+	(x * 255.0).round() as u8
+}
+
+// Reading
+
+pub struct SvgLayout {
+	// View the file was exported from.
+	pub view: Option<String>,
+	pub circles: Vec<SvgCircle>
+}
+
+pub struct SvgCircle {
+	pub id: Option<String>,
+	// Bone name and occurrence, missing on circles drawn in by hand.
+	pub name: Option<(String, usize)>,
+	// Center in normalized coordinates.
+	pub pos: [f32; 2]
+}
+
+pub struct SvgMove {
+	// Bone index in the view, so bones that share a name are moved separately.
+	pub index: usize,
+	pub name: String,
+	pub label: String,
+	pub from: [f32; 2],
+	pub to: [f32; 2]
+}
+
+// Affine transform as `[ a, b, c, d, e, f ]`, mapping (x, y) to (ax + cy + e, bx + dy + f).
+type Transform = [f32; 6];
+
+const IDENTITY: Transform = [ 1.0, 0.0, 0.0, 1.0, 0.0, 0.0 ];
+
+impl SvgLayout {
+	pub fn read(path: &Path) -> Result<Self, String> {
+		let content = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+		Self::parse(&content)
+	}
+	
+	pub fn parse(content: &str) -> Result<Self, String> {
+		let mut reader = Reader::from_str(content);
+		reader.config_mut().trim_text(true);
+		
+		let mut view = None;
+		// Canvas origin and size, in the units circles are placed in.
+		let mut canvas: Option<[f32; 4]> = None;
+		// Transform of each open element, its parents' applied.
+		let mut stack: Vec<Transform> = Vec::new();
+		let mut circles = Vec::new();
+		
+		loop {
+			let (e, empty) = match reader.read_event() {
+				Err(err) => return Err(format!("Invalid XML at {}: {}", reader.error_position(), err)),
+				Ok(Event::Eof) => break,
+				Ok(Event::Start(e)) => (e, false),
+				Ok(Event::Empty(e)) => (e, true),
+				Ok(Event::End(_)) => {
+					stack.pop();
+					continue;
+				},
+				_ => continue
+			};
+			
+			let parent = stack.last().copied().unwrap_or(IDENTITY);
+			let local = e.local_name();
+			let transform = match local.as_ref() {
+				// The root viewBox maps to the canvas instead.
+				b"svg" if canvas.is_none() => {
+					canvas = Some(viewport(&e)?);
+					view = attribute(&e, "data-view");
+					IDENTITY
+				},
+				_ => attribute(&e, "transform").map_or(parent, |x| multiply(parent, parse_transform(&x)))
+			};
+			
+			if matches!(local.as_ref(), b"circle" | b"ellipse") {
+				let id = attribute(&e, "id");
+				let name = attribute(&e, "data-name").map(|name| {
+					let occurrence = attribute(&e, "data-occurrence").and_then(|x| x.trim().parse().ok());
+					(name, occurrence.unwrap_or(0))
+				});
+				if id.is_some() || name.is_some() {
+					let [ x, y ] = [ "cx", "cy" ].map(|name| attribute(&e, name).and_then(|x| number(&x)).unwrap_or(0.0));
+					let [ x, y ] = transform_point(transform, [ x, y ]);
+					let [ left, top, width, height ] = canvas.unwrap_or([ 0.0, 0.0, 1.0, 1.0 ]);
+					circles.push(SvgCircle { id, name, pos: [ (x - left) / width, (y - top) / height ] });
+				}
+			}
+			
+			if !empty {
+				stack.push(transform);
+			}
+		}
+		
+		if canvas.is_none() {
+			return Err("Not an SVG file.".to_string());
+		}
+		Ok(Self { view, circles })
+	}
+	
+	// Moves for the bones of `view` a circle matches, and the circles that matched no bone.
+	// A circle matching a bone an earlier circle already moved is left out as unmatched.
+	pub fn plan(&self, view: &SchemaView) -> (Vec<SvgMove>, Vec<String>) {
+		let ids = circle_ids(view);
+		let mut matched = vec![false; view.bones.len()];
+		let mut moves = Vec::new();
+		let mut unmatched = Vec::new();
+		for circle in &self.circles {
+			let index = match (&circle.name, &circle.id) {
+				(Some((name, occurrence)), _) => (0..view.bones.len())
+					.filter(|&i| view.bones[i].name == *name)
+					.nth(*occurrence),
+				(None, Some(id)) => ids.iter().position(|x| x == id),
+				(None, None) => None
+			};
+			match index {
+				Some(index) if !matched[index] => {
+					matched[index] = true;
+					let bone = &view.bones[index];
+					moves.push(SvgMove {
+						index,
+						name: bone.name.clone(),
+						label: bone.label.clone(),
+						from: [ bone.x, bone.y ],
+						to: circle.pos.map(|x| x.clamp(0.0, 1.0))
+					});
+				},
+				_ => unmatched.push(circle.describe())
+			}
+		}
+		(moves, unmatched)
+	}
+}
+
+impl SvgCircle {
+	fn describe(&self) -> String {
+		match (&self.name, &self.id) {
+			(Some((name, 0)), _) => name.clone(),
+			(Some((name, occurrence)), _) => format!("{} ({})", name, occurrence + 1),
+			(None, Some(id)) => id.clone(),
+			(None, None) => String::new()
+		}
+	}
+}
+
+impl SvgMove {
+	pub fn is_moved(&self) -> bool {
+		// Exported centers are rounded, so movement under that is left alone.
+		(self.to[0] - self.from[0]).abs() > 1e-4 || (self.to[1] - self.from[1]).abs() > 1e-4
+	}
+}
+
+pub fn apply(view: &mut SchemaView, moves: &[SvgMove]) -> usize {
+	let mut moved = 0;
+	for change in moves {
+		let Some(bone) = view.bones.get_mut(change.index) else { continue };
+		[ bone.x, bone.y ] = change.to;
+		moved += 1;
+	}
+	moved
+}
+
+fn attribute(e: &BytesStart, name: &str) -> Option<String> {
+	e.try_get_attribute(name).ok().flatten()
+		.map(|attr| attr.unescape_value().unwrap_or_default().into_owned())
+}
+
+// Lengths in user units, which is all the exported files use. Unit suffixes are dropped.
+fn number(text: &str) -> Option<f32> {
+	let text = text.trim().trim_end_matches(|c: char| c.is_ascii_alphabetic() || c == '%');
+	text.parse().ok()
+}
+
+fn viewport(e: &BytesStart) -> Result<[f32; 4], String> {
+	if let Some(view_box) = attribute(e, "viewBox") {
+		let values: Vec<f32> = view_box.split([ ' ', ',' ])
+			.filter(|x| !x.is_empty())
+			.filter_map(number)
+			.collect();
+		if let [ left, top, width, height ] = values[..] {
+			if width > 0.0 && height > 0.0 {
+				return Ok([ left, top, width, height ]);
+			}
+		}
+	}
+	let size = [ "width", "height" ].map(|name| attribute(e, name).and_then(|x| number(&x)).filter(|x| *x > 0.0));
+	match size {
+		[ Some(width), Some(height) ] => Ok([ 0.0, 0.0, width, height ]),
+		_ => Err("SVG has no size or viewBox.".to_string())
+	}
+}
+
+// Transforms
+
+fn multiply(a: Transform, b: Transform) -> Transform {
+	[
+		a[0] * b[0] + a[2] * b[1],
+		a[1] * b[0] + a[3] * b[1],
+		a[0] * b[2] + a[2] * b[3],
+		a[1] * b[2] + a[3] * b[3],
+		a[0] * b[4] + a[2] * b[5] + a[4],
+		a[1] * b[4] + a[3] * b[5] + a[5]
+	]
+}
+
+fn transform_point(t: Transform, [ x, y ]: [f32; 2]) -> [f32; 2] {
+	[ t[0] * x + t[2] * y + t[4], t[1] * x + t[3] * y + t[5] ]
+}
+
+// A list like `translate(10, 20) rotate(45)`. Unknown or malformed entries are skipped.
+fn parse_transform(text: &str) -> Transform {
+	let mut result = IDENTITY;
+	for item in text.split(')') {
+		let Some((name, args)) = item.split_once('(') else { continue };
+		let args: Vec<f32> = args.split([ ' ', ',' ])
+			.filter(|x| !x.is_empty())
+			.filter_map(number)
+			.collect();
+		
+		let transform = match (name.trim_matches([ ' ', ',' ]), &args[..]) {
+			("matrix", &[ a, b, c, d, e, f ]) => [ a, b, c, d, e, f ],
+			("translate", &[ x ]) => [ 1.0, 0.0, 0.0, 1.0, x, 0.0 ],
+			("translate", &[ x, y ]) => [ 1.0, 0.0, 0.0, 1.0, x, y ],
+			("scale", &[ s ]) => [ s, 0.0, 0.0, s, 0.0, 0.0 ],
+			("scale", &[ x, y ]) => [ x, 0.0, 0.0, y, 0.0, 0.0 ],
+			("rotate", &[ angle ]) => rotation(angle),
+			("rotate", &[ angle, x, y ]) => {
+				let to = [ 1.0, 0.0, 0.0, 1.0, x, y ];
+				let back = [ 1.0, 0.0, 0.0, 1.0, -x, -y ];
+				multiply(multiply(to, rotation(angle)), back)
+			},
+			("skewX", &[ angle ]) => [ 1.0, 0.0, angle.to_radians().tan(), 1.0, 0.0, 0.0 ],
+			("skewY", &[ angle ]) => [ 1.0, angle.to_radians().tan(), 0.0, 1.0, 0.0, 0.0 ],
+			_ => continue
+		};
+		result = multiply(result, transform);
+	}
+	result
+}
+
+fn rotation(angle: f32) -> Transform {
+	let (sin, cos) = angle.to_radians().sin_cos();
+	[ cos, sin, -sin, cos, 0.0, 0.0 ]
+}
\ No newline at end of file
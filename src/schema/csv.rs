@@ -0,0 +1,472 @@
+﻿use crate::schema::data::{SchemaBone, SchemaData, SchemaView};
+use std::{fs::read_to_string, path::Path};
+
+// CSV
+//
+// Bones are written one per row as `view,label,name,x,y`, optionally followed by `px,py`
+// pixel coordinates at a chosen resolution. Columns are matched by header when reading,
+// so they can be reordered or dropped in a spreadsheet.
+
+const HEADER: [&str; 5] = [ "view", "label", "name", "x", "y" ];
+const PIXEL_HEADER: [&str; 2] = [ "px", "py" ];
+
+pub fn export(views: &[&SchemaView], pixels: Option<[u32; 2]>) -> String {
+	let mut header: Vec<&str> = HEADER.to_vec();
+	if pixels.is_some() {
+		header.extend(PIXEL_HEADER);
+	}
+	
+	let mut out = String::new();
+	write_record(&mut out, &header);
+	for view in views {
+		for bone in &view.bones {
+			let mut record = vec![
+				view.name.clone(),
+				bone.label.clone(),
+				bone.name.clone(),
+				bone.x.to_string(),
+				bone.y.to_string()
+			];
+			if let Some([ width, height ]) = pixels {
+				record.push(format!("{:.1}", bone.x * width as f32));
+				record.push(format!("{:.1}", bone.y * height as f32));
+			}
+			write_record(&mut out, &record);
+		}
+	}
+	out
+}
+
+fn write_record<S: AsRef<str>>(out: &mut String, fields: &[S]) {
+	let fields: Vec<String> = fields.iter().map(|field| {
+		let field = field.as_ref();
+		match field.contains([ ',', '"', '\n', '\r' ]) || field.trim() != field {
+			true => format!("\"{}\"", field.replace('"', "\"\"")),
+			false => field.to_string()
+		}
+	}).collect();
+	out.push_str(&fields.join(","));
+	out.push_str("\r\n");
+}
+
+// Reading
+
+pub struct CsvTable {
+	header: Vec<String>,
+	// Records with their line number, for reporting.
+	records: Vec<(usize, Vec<String>)>
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Coordinates {
+	Normalized,
+	Pixels([u32; 2])
+}
+
+pub struct CsvRow {
+	pub line: usize,
+	pub view: String,
+	pub label: String,
+	pub name: String,
+	pub pos: Result<[f32; 2], String>
+}
+
+impl CsvTable {
+	pub fn read(path: &Path) -> Result<Self, String> {
+		let content = read_to_string(path).map_err(|err| err.to_string())?;
+		Self::parse(&content)
+	}
+	
+	pub fn parse(content: &str) -> Result<Self, String> {
+		let mut records = parse_records(content.trim_start_matches('\u{feff}'))?;
+		if records.is_empty() {
+			return Err("File is empty.".to_string());
+		}
+		
+		let (_, header) = records.remove(0);
+		let header: Vec<String> = header.iter().map(|x| x.trim().to_lowercase()).collect();
+		
+		let table = Self { header, records };
+		if table.column("view").is_none() {
+			return Err("Missing a \"view\" column.".to_string());
+		}
+		if table.column("name").is_none() && table.column("label").is_none() {
+			return Err("Missing a \"name\" or \"label\" column.".to_string());
+		}
+		if !table.has_normalized() && !table.has_pixels() {
+			return Err("Missing \"x\" and \"y\" or \"px\" and \"py\" columns.".to_string());
+		}
+		Ok(table)
+	}
+	
+	pub fn has_normalized(&self) -> bool {
+		self.column("x").is_some() && self.column("y").is_some()
+	}
+	
+	pub fn has_pixels(&self) -> bool {
+		self.column("px").is_some() && self.column("py").is_some()
+	}
+	
+	pub fn rows(&self, coords: Coordinates) -> Vec<CsvRow> {
+		let (x, y, scale) = match coords {
+			Coordinates::Normalized => (self.column("x"), self.column("y"), [ 1.0; 2 ]),
+			Coordinates::Pixels([ width, height ]) => (
+				self.column("px"), self.column("py"),
+				[ width.max(1) as f32, height.max(1) as f32 ]
+			)
+		};
+		
+		self.records.iter()
+			.filter(|(_, record)| record.iter().any(|field| !field.trim().is_empty()))
+			.map(|(line, record)| {
+				let field = |col: Option<usize>| col
+					.and_then(|col| record.get(col))
+					.map_or(String::new(), |x| x.trim().to_string());
+				let number = |col: Option<usize>, scale: f32| {
+					let text = field(col);
+					text.parse::<f32>()
+						.map(|value| value / scale)
+						.map_err(|_| format!("Invalid coordinate \"{}\"", text))
+				};
+				
+				CsvRow {
+					line: *line,
+					view: field(self.column("view")),
+					label: field(self.column("label")),
+					name: field(self.column("name")),
+					pos: number(x, scale[0]).and_then(|x| Ok([ x, number(y, scale[1])? ]))
+				}
+			})
+			.collect()
+	}
+	
+	fn column(&self, name: &str) -> Option<usize> {
+		self.header.iter().position(|x| x == name)
+	}
+}
+
+fn parse_records(content: &str) -> Result<Vec<(usize, Vec<String>)>, String> {
+	let mut records = Vec::new();
+	let mut record = Vec::new();
+	let mut field = String::new();
+	let mut quoted = false;
+	let mut line = 1;
+	let mut start = 1;
+	
+	let mut chars = content.chars().peekable();
+	while let Some(c) = chars.next() {
+		match c {
+			'"' if quoted => match chars.peek() {
+				Some('"') => {
+					field.push('"');
+					chars.next();
+				},
+				_ => quoted = false
+			},
+			'"' if field.trim().is_empty() => {
+				field.clear();
+				quoted = true;
+			},
+			',' if !quoted => record.push(std::mem::take(&mut field)),
+			'\r' if !quoted => (),
+			'\n' if !quoted => {
+				record.push(std::mem::take(&mut field));
+				records.push((start, std::mem::take(&mut record)));
+				line += 1;
+				start = line;
+			},
+			c => {
+				if c == '\n' {
+					line += 1;
+				}
+				field.push(c);
+			}
+		}
+	}
+	
+	if quoted {
+		return Err(format!("Unterminated quote starting on line {}.", start));
+	}
+	if !field.is_empty() || !record.is_empty() {
+		record.push(field);
+		records.push((start, record));
+	}
+	Ok(records)
+}
+
+// Import plan
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ChangeKind {
+	Add,
+	Update,
+	Delete,
+	Unchanged,
+	Invalid
+}
+
+pub struct CsvChange {
+	pub kind: ChangeKind,
+	pub view: String,
+	// Bone as it is in the schema and its index in the view, for updates and deletions.
+	pub old: Option<SchemaBone>,
+	pub index: Option<usize>,
+	// Bone as it will be, for additions and updates.
+	pub new: Option<SchemaBone>,
+	pub line: Option<usize>,
+	pub error: Option<String>
+}
+
+impl ChangeKind {
+	pub fn label(self) -> &'static str {
+		match self {
+			ChangeKind::Add => "Add",
+			ChangeKind::Update => "Update",
+			ChangeKind::Delete => "Delete",
+			ChangeKind::Unchanged => "Unchanged",
+			ChangeKind::Invalid => "Invalid"
+		}
+	}
+}
+
+// Matches rows to bones by view and name, or by label when the name doesn't match.
+// Bones without a row are planned for deletion, in views where a row names some bone.
+pub fn plan(data: &SchemaData, rows: &[CsvRow]) -> Vec<CsvChange> {
+	let mut changes = Vec::new();
+	let mut matched: Vec<(String, usize)> = Vec::new();
+	// Views with a row naming a bone. Their bones without a row are planned for deletion.
+	let mut listed: Vec<&str> = Vec::new();
+	
+	for row in rows {
+		let invalid = |error: String| CsvChange {
+			kind: ChangeKind::Invalid,
+			view: row.view.clone(),
+			old: None,
+			index: None,
+			new: None,
+			line: Some(row.line),
+			error: Some(error)
+		};
+		
+		let Some(view) = data.views.iter().find(|view| view.name == row.view) else {
+			changes.push(invalid(format!("Unknown view \"{}\"", row.view)));
+			continue;
+		};
+		if row.name.is_empty() && row.label.is_empty() {
+			changes.push(invalid("Row has no name or label".to_string()));
+			continue;
+		}
+		if !listed.contains(&view.name.as_str()) {
+			listed.push(&view.name);
+		}
+		
+		let is_free = |i: &usize| !matched.contains(&(view.name.clone(), *i));
+		let found = view.bones.iter().enumerate()
+			.filter(|(i, _)| is_free(i))
+			.find(|(_, bone)| !row.name.is_empty() && bone.name == row.name)
+			.or_else(|| view.bones.iter().enumerate()
+				.filter(|(i, _)| is_free(i))
+				.find(|(_, bone)| !row.label.is_empty() && bone.label == row.label));
+		if let Some((i, _)) = found {
+			matched.push((view.name.clone(), i));
+		}
+		
+		// A row with a bad position still holds on to its bone, so a typo isn't read as a deletion.
+		let [ x, y ] = match &row.pos {
+			Ok(pos) => *pos,
+			Err(err) => {
+				let (index, old) = found.map(|(i, bone)| (i, bone.clone())).unzip();
+				changes.push(CsvChange { index, old, ..invalid(err.clone()) });
+				continue;
+			}
+		};
+		
+		let change = match found {
+			Some((i, old)) => {
+				let mut new = old.clone();
+				if !row.label.is_empty() { new.label = row.label.clone() }
+				if !row.name.is_empty() { new.name = row.name.clone() }
+				new.x = x;
+				new.y = y;
+				
+				let moved = (new.x - old.x).abs() > f32::EPSILON || (new.y - old.y).abs() > f32::EPSILON;
+				let kind = match moved || new.label != old.label || new.name != old.name {
+					true => ChangeKind::Update,
+					false => ChangeKind::Unchanged
+				};
+				CsvChange { kind, view: view.name.clone(), old: Some(old.clone()), index: Some(i), new: Some(new), line: Some(row.line), error: None }
+			},
+			None => {
+				let label = match row.label.is_empty() {
+					true => row.name.clone(),
+					false => row.label.clone()
+				};
+				let new = SchemaBone { label, name: row.name.clone(), x, y, ..SchemaBone::default() };
+				CsvChange { kind: ChangeKind::Add, view: view.name.clone(), old: None, index: None, new: Some(new), line: Some(row.line), error: None }
+			}
+		};
+		changes.push(change);
+	}
+	
+	for view in &data.views {
+		if !listed.contains(&view.name.as_str()) { continue }
+		for (i, bone) in view.bones.iter().enumerate() {
+			if matched.contains(&(view.name.clone(), i)) { continue }
+			changes.push(CsvChange {
+				kind: ChangeKind::Delete,
+				view: view.name.clone(),
+				old: Some(bone.clone()),
+				index: Some(i),
+				new: None,
+				line: None,
+				error: None
+			});
+		}
+	}
+	
+	changes
+}
+
+// Changes go by the bone indices `plan` matched, so bones sharing a name or label can't be
+// mixed up. Updates come first, then deletions from the back so earlier indices hold, then additions.
+pub fn apply(data: &mut SchemaData, changes: &[CsvChange]) {
+	for view in &mut data.views {
+		let changes: Vec<&CsvChange> = changes.iter().filter(|change| change.view == view.name).collect();
+		
+		for change in &changes {
+			let (ChangeKind::Update, Some(i), Some(new)) = (change.kind, change.index, &change.new) else { continue };
+			if let Some(bone) = view.bones.get_mut(i) {
+				*bone = new.clone();
+			}
+		}
+		
+		let mut deleted: Vec<usize> = changes.iter()
+			.filter(|change| change.kind == ChangeKind::Delete)
+			.filter_map(|change| change.index)
+			.collect();
+		deleted.sort_unstable_by(|a, b| b.cmp(a));
+		deleted.dedup();
+		for i in deleted {
+			if i < view.bones.len() {
+				view.bones.remove(i);
+			}
+		}
+		
+		for change in &changes {
+			if let (ChangeKind::Add, Some(new)) = (change.kind, &change.new) {
+				view.bones.push(new.clone());
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	fn fields(records: &[(usize, Vec<String>)]) -> Vec<Vec<&str>> {
+		records.iter().map(|(_, record)| record.iter().map(String::as_str).collect()).collect()
+	}
+	
+	fn bone(name: &str, x: f32, y: f32) -> SchemaBone {
+		SchemaBone { label: name.to_uppercase(), name: name.to_string(), x, y, ..SchemaBone::default() }
+	}
+	
+	fn schema(bones: Vec<SchemaBone>) -> SchemaData {
+		let view = SchemaView { name: "Front".to_string(), bones, ..SchemaView::default() };
+		SchemaData { views: vec![ view ], ..SchemaData::default() }
+	}
+	
+	fn rows(content: &str) -> Vec<CsvRow> {
+		CsvTable::parse(content).unwrap().rows(Coordinates::Normalized)
+	}
+	
+	fn kinds(changes: &[CsvChange]) -> Vec<(&'static str, Option<usize>)> {
+		changes.iter().map(|change| (change.kind.label(), change.index)).collect()
+	}
+	
+	#[test]
+	fn quoted_fields_and_escaped_quotes() {
+		let records = parse_records("a,\"b,c\",\"say \"\"hi\"\"\"\n").unwrap();
+		assert_eq!(fields(&records), vec![ vec![ "a", "b,c", "say \"hi\"" ] ]);
+	}
+	
+	#[test]
+	fn crlf_and_newlines_inside_quotes() {
+		let records = parse_records("a,b\r\n\"two\r\nlines\",c\r\nd,e").unwrap();
+		assert_eq!(fields(&records), vec![ vec![ "a", "b" ], vec![ "two\r\nlines", "c" ], vec![ "d", "e" ] ]);
+		let lines: Vec<usize> = records.iter().map(|(line, _)| *line).collect();
+		assert_eq!(lines, vec![ 1, 2, 4 ]);
+	}
+	
+	#[test]
+	fn unterminated_quote_is_an_error() {
+		let err = parse_records("a,b\nc,\"open\nd,e\n").err().unwrap();
+		assert_eq!(err, "Unterminated quote starting on line 2.");
+	}
+	
+	#[test]
+	fn export_reads_back() {
+		let mut data = schema(vec![ bone("a", 0.25, 0.5) ]);
+		data.views[0].bones[0].label = "Left, \"upper\" arm".to_string();
+		let rows = rows(&export(&[ &data.views[0] ], None));
+		assert_eq!(rows.len(), 1);
+		assert_eq!(rows[0].label, "Left, \"upper\" arm");
+		assert_eq!(rows[0].pos, Ok([ 0.25, 0.5 ]));
+	}
+	
+	#[test]
+	fn columns_are_matched_by_header() {
+		let rows = rows("Name,Y,x,View\nj_kao,0.2,0.1,Front\n");
+		assert_eq!((rows[0].view.as_str(), rows[0].name.as_str(), rows[0].label.as_str()), ("Front", "j_kao", ""));
+		assert_eq!(rows[0].pos, Ok([ 0.1, 0.2 ]));
+	}
+	
+	#[test]
+	fn pixels_are_scaled_by_the_resolution() {
+		let table = CsvTable::parse("view,name,px,py\nFront,a,50,25\n").unwrap();
+		assert!(!table.has_normalized() && table.has_pixels());
+		let rows = table.rows(Coordinates::Pixels([ 200, 100 ]));
+		assert_eq!(rows[0].pos, Ok([ 0.25, 0.25 ]));
+	}
+	
+	#[test]
+	fn missing_columns_are_reported() {
+		assert!(CsvTable::parse("name,x,y\na,0,0\n").is_err());
+		assert!(CsvTable::parse("view,name\nFront,a\n").is_err());
+		assert!(CsvTable::parse("").is_err());
+	}
+	
+	#[test]
+	fn plan_adds_updates_and_deletes() {
+		let data = schema(vec![ bone("a", 0.1, 0.1), bone("b", 0.2, 0.2), bone("c", 0.3, 0.3) ]);
+		let changes = plan(&data, &rows("view,name,x,y\nFront,a,0.1,0.1\nFront,b,0.5,0.5\nFront,d,0.4,0.4\n"));
+		assert_eq!(kinds(&changes), vec![ ("Unchanged", Some(0)), ("Update", Some(1)), ("Add", None), ("Delete", Some(2)) ]);
+	}
+	
+	#[test]
+	fn bad_row_keeps_its_bone() {
+		let data = schema(vec![ bone("a", 0.1, 0.1), bone("b", 0.2, 0.2) ]);
+		let changes = plan(&data, &rows("view,name,x,y\nFront,a,0.1,0.1\nFront,b,0.2x,0.2\n"));
+		assert_eq!(kinds(&changes), vec![ ("Unchanged", Some(0)), ("Invalid", Some(1)) ]);
+	}
+	
+	#[test]
+	fn view_with_only_unnamed_rows_is_left_alone() {
+		let data = schema(vec![ bone("a", 0.1, 0.1) ]);
+		let changes = plan(&data, &rows("view,name,x,y\nFront,,0.1,0.1\n"));
+		assert_eq!(kinds(&changes), vec![ ("Invalid", None) ]);
+	}
+	
+	#[test]
+	fn apply_goes_by_the_planned_indices() {
+		// Both bones share a name, so only the indices tell them apart.
+		let mut data = schema(vec![ bone("a", 0.1, 0.1), bone("a", 0.2, 0.2), bone("b", 0.3, 0.3) ]);
+		let changes = plan(&data, &rows("view,name,x,y\nFront,a,0.5,0.5\nFront,c,0.6,0.6\n"));
+		assert_eq!(kinds(&changes), vec![ ("Update", Some(0)), ("Add", None), ("Delete", Some(1)), ("Delete", Some(2)) ]);
+		
+		apply(&mut data, &changes);
+		let bones: Vec<(&str, f32)> = data.views[0].bones.iter().map(|bone| (bone.name.as_str(), bone.x)).collect();
+		assert_eq!(bones, vec![ ("a", 0.5), ("c", 0.6) ]);
+	}
+}
\ No newline at end of file
@@ -1,36 +1,131 @@
-﻿#[derive(Default)]
+﻿use std::collections::{BTreeMap, BTreeSet};
+
+// Translated text keyed by language code. The untranslated field holds the fallback.
+pub type Translations = BTreeMap<String, String>;
+
+#[derive(Default, Clone, PartialEq)]
 pub struct SchemaData {
+	// Language of the untranslated labels.
+	pub language: Option<String>,
 	pub views: Vec<SchemaView>
 }
 
 impl SchemaData {
 	pub fn get_view(&mut self, name: String) -> Option<&mut SchemaView> {
-		for view in &mut self.views {
-			if view.name == name {
-				return Some(view);
+		self.views.iter_mut().find(|view| view.name == name)
+	}
+	
+	// Every language with at least one translation.
+	pub fn languages(&self) -> BTreeSet<String> {
+		let mut languages = BTreeSet::new();
+		for view in &self.views {
+			languages.extend(view.names.keys().cloned());
+			for bone in &view.bones {
+				languages.extend(bone.labels.keys().cloned());
+			}
+		}
+		languages
+	}
+	
+	// `name`, numbered when another view already has it.
+	pub fn unique_view_name(&self, name: &str) -> String {
+		let mut unique = name.to_string();
+		let mut n = 2;
+		while self.views.iter().any(|view| view.name == unique) {
+			unique = format!("{} ({})", name, n);
+			n += 1;
+		}
+		unique
+	}
+	
+	// Adds a view from another schema, renamed if the name is taken.
+	// With `merge` its bones go into the view of the same name instead.
+	pub fn import_view(&mut self, mut view: SchemaView, merge: bool) {
+		match self.views.iter_mut().find(|x| x.name == view.name) {
+			Some(existing) if merge => existing.merge_from(&view),
+			_ => {
+				view.name = self.unique_view_name(&view.name);
+				self.views.push(view);
 			}
 		}
-		
-		None
 	}
 }
 
-#[derive(Default)]
+#[derive(Default, Clone, PartialEq)]
 pub struct SchemaView {
 	pub name: String,
+	pub names: Translations,
 	pub bones: Vec<SchemaBone>,
 	pub images: Vec<SchemaImage>
 }
 
-#[derive(Default)]
+impl SchemaView {
+	pub fn add_bone(&mut self) -> &SchemaBone {
+		// Counting from the bone count, past numbers left taken by earlier deletes.
+		let mut n = self.bones.len();
+		while self.bones.iter().any(|bone| bone.name == format!("bone{}", n)) {
+			n += 1;
+		}
+		let label = self.unique_label(&format!("Bone{}", n));
+		self.bones.push(SchemaBone {
+			label,
+			name: format!("bone{}", n),
+			..SchemaBone::default()
+		});
+		self.bones.last().unwrap()
+	}
+	
+	pub fn display_name(&self, lang: Option<&str>) -> &str {
+		translate(&self.name, &self.names, lang)
+	}
+	
+	// Labels identify bones in the editor, so repeats are numbered.
+	pub fn unique_label(&self, base: &str) -> String {
+		let mut label = base.to_string();
+		let mut n = 2;
+		while self.bones.iter().any(|bone| bone.label == label) {
+			label = format!("{} {}", base, n);
+			n += 1;
+		}
+		label
+	}
+	
+	// Adds the bones and images of `other` this view doesn't have, by bone name and image file.
+	pub fn merge_from(&mut self, other: &SchemaView) {
+		for bone in &other.bones {
+			if self.bones.iter().any(|x| x.name == bone.name) { continue }
+			let label = self.unique_label(&bone.label);
+			self.bones.push(SchemaBone { label, ..bone.clone() });
+		}
+		for img in &other.images {
+			if !self.images.iter().any(|x| x.file == img.file) {
+				self.images.push(img.clone());
+			}
+		}
+	}
+}
+
+#[derive(Default, Clone, PartialEq)]
 pub struct SchemaBone {
 	pub label: String,
+	pub labels: Translations,
 	pub name: String,
 	pub x: f32,
 	pub y: f32
 }
 
-#[derive(Default, Clone)]
+impl SchemaBone {
+	pub fn display_label(&self, lang: Option<&str>) -> &str {
+		translate(&self.label, &self.labels, lang)
+	}
+}
+
+#[derive(Default, Clone, PartialEq)]
 pub struct SchemaImage {
 	pub file: String
+}
+
+fn translate<'a>(fallback: &'a str, translations: &'a Translations, lang: Option<&str>) -> &'a str {
+	lang.and_then(|lang| translations.get(lang))
+		.map_or(fallback, |text| text.as_str())
 }
\ No newline at end of file
@@ -0,0 +1,372 @@
+﻿use crate::schema::data::{SchemaBone, SchemaData, SchemaView};
+use std::fmt;
+use serde::Serialize;
+
+// Semantic diff
+//
+// Compares schemas by meaning rather than text: views are matched by name and bones by
+// bone name, so reordering and float formatting don't show up. Unmatched views sharing most
+// of their bones, and unmatched bones sharing a label, are reported as renamed.
+
+pub const DEFAULT_THRESHOLD: f32 = 0.001;
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+	Added,
+	Removed,
+	Changed
+}
+
+#[derive(Serialize, Default)]
+pub struct SchemaDiff {
+	pub views: Vec<ViewDiff>
+}
+
+#[derive(Serialize)]
+pub struct ViewDiff {
+	pub status: Status,
+	pub name: String,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub old_name: Option<String>,
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	pub images_added: Vec<String>,
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	pub images_removed: Vec<String>,
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	pub bones: Vec<BoneDiff>
+}
+
+#[derive(Serialize)]
+pub struct BoneDiff {
+	pub status: Status,
+	pub name: String,
+	pub label: String,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub old_name: Option<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub old_label: Option<String>,
+	// Position before the change, for removed and moved bones.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub from: Option<[f32; 2]>,
+	// Position after the change, for added and moved bones.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub to: Option<[f32; 2]>
+}
+
+impl SchemaDiff {
+	// Changes from `old` to `new`. Bones moving less than `threshold` count as unchanged.
+	pub fn compute(old: &SchemaData, new: &SchemaData, threshold: f32) -> Self {
+		let pairs = match_views(&old.views, &new.views);
+		let mut views = Vec::new();
+		
+		for (old_view, new_view) in pairs {
+			match (old_view, new_view) {
+				(Some(old), Some(new)) => {
+					let diff = diff_view(old, new, threshold);
+					let renamed = old.name != new.name;
+					if renamed || !diff.images_added.is_empty() || !diff.images_removed.is_empty() || !diff.bones.is_empty() {
+						views.push(diff);
+					}
+				},
+				(None, Some(new)) => views.push(whole_view(new, Status::Added)),
+				(Some(old), None) => views.push(whole_view(old, Status::Removed)),
+				(None, None) => ()
+			}
+		}
+		
+		Self { views }
+	}
+	
+	pub fn is_empty(&self) -> bool {
+		self.views.is_empty()
+	}
+}
+
+impl BoneDiff {
+	pub fn distance(&self) -> Option<f32> {
+		let (from, to) = self.from.zip(self.to)?;
+		Some(((to[0] - from[0]).powi(2) + (to[1] - from[1]).powi(2)).sqrt())
+	}
+}
+
+// Matching
+
+// Pairs views by name, then pairs the rest up as renames by the bones they share.
+fn match_views<'a>(old: &'a [SchemaView], new: &'a [SchemaView]) -> Vec<(Option<&'a SchemaView>, Option<&'a SchemaView>)> {
+	let mut old_left: Vec<&SchemaView> = old.iter().filter(|x| !new.iter().any(|y| y.name == x.name)).collect();
+	
+	let mut pairs = Vec::new();
+	for view in new {
+		let paired = match old.iter().find(|x| x.name == view.name) {
+			Some(old) => Some(old),
+			None => {
+				let best = old_left.iter().enumerate()
+					.map(|(i, old)| (i, similarity(old, view)))
+					.filter(|(_, score)| *score >= 0.5)
+					.max_by(|a, b| a.1.total_cmp(&b.1))
+					.map(|(i, _)| i);
+				best.map(|i| old_left.remove(i))
+			}
+		};
+		pairs.push((paired, Some(view)));
+	}
+	pairs.extend(old_left.into_iter().map(|view| (Some(view), None)));
+	pairs
+}
+
+// Share of bone names in common, against the larger view.
+fn similarity(a: &SchemaView, b: &SchemaView) -> f32 {
+	let total = a.bones.len().max(b.bones.len());
+	if total == 0 {
+		let same_images = !a.images.is_empty() && a.images == b.images;
+		return if same_images { 1.0 } else { 0.0 };
+	}
+	let shared = a.bones.iter().filter(|x| b.bones.iter().any(|y| y.name == x.name)).count();
+	shared as f32 / total as f32
+}
+
+fn whole_view(view: &SchemaView, status: Status) -> ViewDiff {
+	let (images, bones) = (
+		view.images.iter().map(|x| x.file.clone()).collect(),
+		view.bones.iter().map(|bone| whole_bone(bone, status)).collect()
+	);
+	let (images_added, images_removed) = match status {
+		Status::Removed => (Vec::new(), images),
+		_ => (images, Vec::new())
+	};
+	ViewDiff { status, name: view.name.clone(), old_name: None, images_added, images_removed, bones }
+}
+
+fn whole_bone(bone: &SchemaBone, status: Status) -> BoneDiff {
+	let pos = Some([ bone.x, bone.y ]);
+	BoneDiff {
+		status,
+		name: bone.name.clone(),
+		label: bone.label.clone(),
+		old_name: None,
+		old_label: None,
+		from: if status == Status::Removed { pos } else { None },
+		to: if status == Status::Added { pos } else { None }
+	}
+}
+
+fn diff_view(old: &SchemaView, new: &SchemaView, threshold: f32) -> ViewDiff {
+	let images_added = new.images.iter()
+		.filter(|x| !old.images.iter().any(|y| y.file == x.file))
+		.map(|x| x.file.clone())
+		.collect();
+	let images_removed = old.images.iter()
+		.filter(|x| !new.images.iter().any(|y| y.file == x.file))
+		.map(|x| x.file.clone())
+		.collect();
+	
+	// Bones pair up by name first, then by label for bones whose name changed.
+	let mut used = vec![ false; old.bones.len() ];
+	let mut pairs: Vec<(Option<usize>, &SchemaBone)> = Vec::new();
+	for bone in &new.bones {
+		let found = (0..old.bones.len()).find(|i| !used[*i] && old.bones[*i].name == bone.name);
+		if let Some(i) = found {
+			used[i] = true;
+		}
+		pairs.push((found, bone));
+	}
+	for pair in pairs.iter_mut().filter(|(found, _)| found.is_none()) {
+		let found = (0..old.bones.len()).find(|i| !used[*i] && old.bones[*i].label == pair.1.label);
+		if let Some(i) = found {
+			used[i] = true;
+			pair.0 = Some(i);
+		}
+	}
+	
+	let mut bones = Vec::new();
+	for (found, bone) in pairs {
+		let Some(i) = found else {
+			bones.push(whole_bone(bone, Status::Added));
+			continue;
+		};
+		let prev = &old.bones[i];
+		let moved = ((bone.x - prev.x).powi(2) + (bone.y - prev.y).powi(2)).sqrt() > threshold;
+		let old_name = (prev.name != bone.name).then(|| prev.name.clone());
+		let old_label = (prev.label != bone.label).then(|| prev.label.clone());
+		if !moved && old_name.is_none() && old_label.is_none() { continue }
+		
+		bones.push(BoneDiff {
+			status: Status::Changed,
+			name: bone.name.clone(),
+			label: bone.label.clone(),
+			old_name,
+			old_label,
+			from: moved.then_some([ prev.x, prev.y ]),
+			to: moved.then_some([ bone.x, bone.y ])
+		});
+	}
+	for (i, bone) in old.bones.iter().enumerate() {
+		if !used[i] {
+			bones.push(whole_bone(bone, Status::Removed));
+		}
+	}
+	
+	ViewDiff {
+		status: Status::Changed,
+		name: new.name.clone(),
+		old_name: (old.name != new.name).then(|| old.name.clone()),
+		images_added,
+		images_removed,
+		bones
+	}
+}
+
+// Text output
+
+impl fmt::Display for SchemaDiff {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		if self.is_empty() {
+			return writeln!(f, "No differences.");
+		}
+		for view in &self.views {
+			writeln!(f, "{}", view.summary())?;
+			if view.status != Status::Changed { continue }
+			for file in &view.images_added {
+				writeln!(f, "  + image {}", file)?;
+			}
+			for file in &view.images_removed {
+				writeln!(f, "  - image {}", file)?;
+			}
+			for bone in &view.bones {
+				writeln!(f, "  {}", bone.summary())?;
+			}
+		}
+		Ok(())
+	}
+}
+
+impl ViewDiff {
+	pub fn summary(&self) -> String {
+		let bones = format!("{} bone(s)", self.bones.len());
+		match (self.status, &self.old_name) {
+			(Status::Added, _) => format!("+ view \"{}\" ({})", self.name, bones),
+			(Status::Removed, _) => format!("- view \"{}\" ({})", self.name, bones),
+			(Status::Changed, Some(old)) => format!("~ view \"{}\" (renamed from \"{}\")", self.name, old),
+			(Status::Changed, None) => format!("~ view \"{}\"", self.name)
+		}
+	}
+}
+
+impl BoneDiff {
+	pub fn summary(&self) -> String {
+		let pos = |[ x, y ]: [f32; 2]| format!("({:.3}, {:.3})", x, y);
+		match self.status {
+			Status::Added => format!("+ bone {} \"{}\" at {}", self.name, self.label, self.to.map(pos).unwrap_or_default()),
+			Status::Removed => format!("- bone {} \"{}\"", self.name, self.label),
+			Status::Changed => {
+				let mut parts = Vec::new();
+				if let Some(old) = &self.old_name {
+					parts.push(format!("renamed from {}", old));
+				}
+				if let Some(old) = &self.old_label {
+					parts.push(format!("relabeled from \"{}\"", old));
+				}
+				if let (Some(from), Some(to), Some(distance)) = (self.from, self.to, self.distance()) {
+					parts.push(format!("moved {:.3} {} -> {}", distance, pos(from), pos(to)));
+				}
+				format!("~ bone {} \"{}\": {}", self.name, self.label, parts.join(", "))
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::json;
+	
+	fn bone(name: &str, label: &str, x: f32, y: f32) -> SchemaBone {
+		SchemaBone { label: label.to_string(), name: name.to_string(), x, y, ..SchemaBone::default() }
+	}
+	
+	fn view(name: &str, names: &[&str]) -> SchemaView {
+		let bones = names.iter().map(|name| bone(name, name, 0.5, 0.5)).collect();
+		SchemaView { name: name.to_string(), bones, ..SchemaView::default() }
+	}
+	
+	fn schema(views: Vec<SchemaView>) -> SchemaData {
+		SchemaData { views, ..SchemaData::default() }
+	}
+	
+	fn summary(diff: &SchemaDiff) -> Vec<String> {
+		diff.views.iter().map(ViewDiff::summary).collect()
+	}
+	
+	#[test]
+	fn unchanged_schemas_have_no_diff() {
+		let data = schema(vec![ view("Front", &[ "a", "b" ]) ]);
+		assert!(SchemaDiff::compute(&data, &data, DEFAULT_THRESHOLD).is_empty());
+	}
+	
+	#[test]
+	fn view_sharing_half_its_bones_is_renamed() {
+		let old = schema(vec![ view("Front", &[ "a", "b", "c", "d" ]) ]);
+		let new = schema(vec![ view("Face", &[ "a", "b", "x", "y" ]) ]);
+		
+		let diff = SchemaDiff::compute(&old, &new, DEFAULT_THRESHOLD);
+		assert_eq!(summary(&diff), vec![ "~ view \"Face\" (renamed from \"Front\")" ]);
+	}
+	
+	#[test]
+	fn view_sharing_under_half_its_bones_is_replaced() {
+		let old = schema(vec![ view("Front", &[ "a", "b", "c", "d" ]) ]);
+		let new = schema(vec![ view("Face", &[ "a", "x", "y", "z" ]) ]);
+		
+		let diff = SchemaDiff::compute(&old, &new, DEFAULT_THRESHOLD);
+		assert_eq!(summary(&diff), vec![ "+ view \"Face\" (4 bone(s))", "- view \"Front\" (4 bone(s))" ]);
+	}
+	
+	#[test]
+	fn renamed_bone_pairs_by_label() {
+		let old = schema(vec![ SchemaView { name: "Front".to_string(), bones: vec![ bone("j_kao", "Head", 0.5, 0.5) ], ..SchemaView::default() } ]);
+		let new = schema(vec![ SchemaView { name: "Front".to_string(), bones: vec![ bone("j_head", "Head", 0.5, 0.5) ], ..SchemaView::default() } ]);
+		
+		let diff = SchemaDiff::compute(&old, &new, DEFAULT_THRESHOLD);
+		let bones = &diff.views[0].bones;
+		assert_eq!(bones.len(), 1);
+		assert_eq!(bones[0].status, Status::Changed);
+		assert_eq!(bones[0].old_name.as_deref(), Some("j_kao"));
+		assert_eq!(bones[0].summary(), "~ bone j_head \"Head\": renamed from j_kao");
+	}
+	
+	#[test]
+	fn moves_under_the_threshold_are_ignored() {
+		let old = schema(vec![ view("Front", &[ "a" ]) ]);
+		let mut new = old.clone();
+		new.views[0].bones[0].x += 0.05;
+		
+		assert!(SchemaDiff::compute(&old, &new, 0.1).is_empty());
+		
+		let diff = SchemaDiff::compute(&old, &new, 0.01);
+		let bone = &diff.views[0].bones[0];
+		assert_eq!(bone.from, Some([ 0.5, 0.5 ]));
+		assert_eq!(bone.to, Some([ 0.55, 0.5 ]));
+	}
+	
+	#[test]
+	fn json_lists_only_what_changed() {
+		let old = schema(vec![ view("Front", &[ "a", "b" ]) ]);
+		let mut new = schema(vec![ view("Front", &[ "a", "c" ]) ]);
+		new.views[0].bones[0].y = 0.25;
+		
+		let diff = SchemaDiff::compute(&old, &new, DEFAULT_THRESHOLD);
+		let value = serde_json::to_value(&diff).unwrap();
+		assert_eq!(value, json!({
+			"views": [ {
+				"status": "changed",
+				"name": "Front",
+				"bones": [
+					{ "status": "changed", "name": "a", "label": "a", "from": [ 0.5, 0.5 ], "to": [ 0.5, 0.25 ] },
+					{ "status": "added", "name": "c", "label": "c", "to": [ 0.5, 0.5 ] },
+					{ "status": "removed", "name": "b", "label": "b", "from": [ 0.5, 0.5 ] }
+				]
+			} ]
+		}));
+	}
+}
\ No newline at end of file
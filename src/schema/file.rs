@@ -1,25 +1,60 @@
-﻿use crate::schema::{data::SchemaData, SchemaSerializer};
+﻿use crate::schema::{data::SchemaData, project::SchemaProject, SchemaSerializer};
 use std::{io::Write, fs::OpenOptions, path::PathBuf};
 
 #[derive(Default)]
 pub struct SchemaFile {
 	pub path: Option<PathBuf>,
-	pub data: SchemaData
+	pub data: SchemaData,
+	pub project: SchemaProject,
+	// Snapshot of the last saved state, compared against to detect changes.
+	saved: (SchemaData, SchemaProject)
 }
 
 impl SchemaFile {
-	pub fn write(&self, path: &PathBuf) {
+	pub fn open(path: PathBuf) -> Result<Self, String> {
+		let data = SchemaData::try_deserialize(path.clone())?;
+		let project = SchemaProject::read(&path);
+		Ok(Self {
+			path: Some(path),
+			saved: (data.clone(), project.clone()),
+			data,
+			project
+		})
+	}
+	
+	pub fn is_dirty(&self) -> bool {
+		self.data != self.saved.0 || self.project != self.saved.1
+	}
+	
+	// Schema as it was last opened or saved.
+	pub fn saved_data(&self) -> &SchemaData {
+		&self.saved.0
+	}
+	
+	pub fn display_name(&self) -> String {
+		self.path.as_ref()
+			.and_then(|path| path.file_name())
+			.map_or("Untitled".to_string(), |name| name.to_string_lossy().into_owned())
+	}
+	
+	pub fn write(&mut self, path: &PathBuf) -> Result<(), String> {
+		let buffer = self.data.serialize()
+			.map_err(|err| format!("Failed to serialize schema: {}", err))?;
+		
 		let mut file = OpenOptions::new()
 			.create(true)
 			.write(true)
+			.truncate(true)
 			.open(path)
-			.expect("Failed to open file.");
-		
-		let buffer = self.data.serialize()
-			.expect("Failed to serialize file.");
+			.map_err(|err| format!("Failed to open {}: {}", path.display(), err))?;
 		
 		file.write_all(&buffer)
 			.and(file.set_len(buffer.len() as u64))
-			.expect("Failed to write file.");
+			.map_err(|err| format!("Failed to write {}: {}", path.display(), err))?;
+		
+		// The schema stays marked as changed until its project is written too.
+		self.project.write(path)?;
+		self.saved = (self.data.clone(), self.project.clone());
+		Ok(())
 	}
 }
\ No newline at end of file
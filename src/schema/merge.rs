@@ -0,0 +1,444 @@
+﻿use crate::schema::data::{SchemaBone, SchemaData, SchemaImage, SchemaView, Translations};
+use std::fmt;
+
+// Three-way merge
+//
+// Merges two edited copies of a schema against their common base. Views are keyed by name and
+// bones by bone name within their view, so a rename reads as a removal and an addition.
+// Items sharing a name are told apart by their order among those with that name.
+// A change made on one side only is taken as is. Fields both sides changed differently, and
+// items one side deleted while the other changed, become conflicts settled by picking a side.
+
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum Side {
+	#[default]
+	Ours,
+	Theirs
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+	Language,
+	// Deleted on one side and changed on the other.
+	View,
+	ViewNames,
+	Bone,
+	Label,
+	Labels,
+	Position
+}
+
+pub struct Conflict {
+	pub view: Option<String>,
+	pub bone: Option<String>,
+	pub field: Field,
+	// Each side's value, as text.
+	pub ours: String,
+	pub theirs: String,
+	pub side: Side
+}
+
+pub struct SchemaMerge {
+	base: SchemaData,
+	ours: SchemaData,
+	theirs: SchemaData,
+	pub conflicts: Vec<Conflict>
+}
+
+impl SchemaMerge {
+	pub fn new(base: SchemaData, ours: SchemaData, theirs: SchemaData) -> Self {
+		let mut merger = Merger::default();
+		merger.data(&base, &ours, &theirs);
+		Self { base, ours, theirs, conflicts: merger.conflicts }
+	}
+	
+	pub fn is_clean(&self) -> bool {
+		self.conflicts.is_empty()
+	}
+	
+	pub fn pick_all(&mut self, side: Side) {
+		for conflict in &mut self.conflicts {
+			conflict.side = side;
+		}
+	}
+	
+	// Merged schema with each conflict settled by its picked side.
+	pub fn result(&self) -> SchemaData {
+		let sides: Vec<Side> = self.conflicts.iter().map(|x| x.side).collect();
+		let mut merger = Merger { sides: &sides, ..Merger::default() };
+		merger.data(&self.base, &self.ours, &self.theirs)
+	}
+}
+
+impl Field {
+	pub fn label(self) -> &'static str {
+		match self {
+			Field::Language => "language",
+			Field::View => "view",
+			Field::ViewNames => "view names",
+			Field::Bone => "bone",
+			Field::Label => "label",
+			Field::Labels => "label translations",
+			Field::Position => "position"
+		}
+	}
+}
+
+impl fmt::Display for Conflict {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		if let Some(view) = &self.view {
+			write!(f, "view \"{}\" ", view)?;
+		}
+		if let Some(bone) = &self.bone {
+			write!(f, "bone {} ", bone)?;
+		}
+		write!(f, "{}: ours {}, theirs {}", self.field.label(), self.ours, self.theirs)
+	}
+}
+
+// Merging
+
+// View and bone a field belongs to.
+type Location<'a> = (Option<&'a str>, Option<&'a str>);
+
+#[derive(Default)]
+struct Merger<'a> {
+	// Sides picked for conflicts in the order they come up. Unpicked conflicts keep ours.
+	sides: &'a [Side],
+	conflicts: Vec<Conflict>
+}
+
+impl Merger<'_> {
+	fn data(&mut self, base: &SchemaData, ours: &SchemaData, theirs: &SchemaData) -> SchemaData {
+		let language = self.value(
+			(None, None), Field::Language,
+			Some(&base.language), &ours.language, &theirs.language,
+			|x| x.as_ref().map_or("(none)".to_string(), |x| format!("\"{}\"", x))
+		);
+		
+		let mut views = Vec::new();
+		for key in keys(&ours.views, &theirs.views, |x| &x.name) {
+			let find = |data: &SchemaData| find(&data.views, &key, |x| &x.name).cloned();
+			let (base, ours, theirs) = (find(base), find(ours), find(theirs));
+			
+			let name = describe_key(&key);
+			let kept = self.presence((Some(name.as_str()), None), Field::View, base.as_ref(), ours.as_ref(), theirs.as_ref());
+			let view = match (kept, ours, theirs) {
+				(false, _, _) => continue,
+				(true, Some(ours), Some(theirs)) => self.view(base.as_ref(), &ours, &theirs),
+				(true, Some(view), None) | (true, None, Some(view)) => view,
+				(true, None, None) => continue
+			};
+			views.push(view);
+		}
+		
+		SchemaData { language, views }
+	}
+	
+	fn view(&mut self, base: Option<&SchemaView>, ours: &SchemaView, theirs: &SchemaView) -> SchemaView {
+		let at = (Some(ours.name.as_str()), None);
+		let names = self.value(
+			at, Field::ViewNames,
+			base.map(|x| &x.names), &ours.names, &theirs.names,
+			describe_translations
+		);
+		
+		// Images are a set: additions and removals from both sides are kept.
+		let base_images = base.map_or(&[][..], |x| &x.images[..]);
+		let has = |images: &[SchemaImage], img: &SchemaImage| images.iter().any(|x| x.file == img.file);
+		let mut images: Vec<SchemaImage> = ours.images.iter()
+			.filter(|img| has(&theirs.images, img) || !has(base_images, img))
+			.cloned()
+			.collect();
+		for img in &theirs.images {
+			if !has(&images, img) && !has(base_images, img) {
+				images.push(img.clone());
+			}
+		}
+		
+		let mut bones = Vec::new();
+		for key in keys(&ours.bones, &theirs.bones, |x| &x.name) {
+			let find = |view: Option<&SchemaView>| find(&view?.bones, &key, |x| &x.name).cloned();
+			let (base_bone, ours_bone, theirs_bone) = (find(base), find(Some(ours)), find(Some(theirs)));
+			
+			let name = describe_key(&key);
+			let at = (Some(ours.name.as_str()), Some(name.as_str()));
+			let kept = self.presence(at, Field::Bone, base_bone.as_ref(), ours_bone.as_ref(), theirs_bone.as_ref());
+			let bone = match (kept, ours_bone, theirs_bone) {
+				(false, _, _) => continue,
+				(true, Some(bone), Some(theirs)) => self.bone(at, base_bone.as_ref(), bone, &theirs),
+				(true, Some(bone), None) | (true, None, Some(bone)) => bone,
+				(true, None, None) => continue
+			};
+			bones.push(bone);
+		}
+		
+		SchemaView { name: ours.name.clone(), names, bones, images }
+	}
+	
+	fn bone(&mut self, at: Location<'_>, base: Option<&SchemaBone>, ours: SchemaBone, theirs: &SchemaBone) -> SchemaBone {
+		let label = self.value(
+			at, Field::Label,
+			base.map(|x| &x.label), &ours.label, &theirs.label,
+			|x| format!("\"{}\"", x)
+		);
+		let labels = self.value(
+			at, Field::Labels,
+			base.map(|x| &x.labels), &ours.labels, &theirs.labels,
+			describe_translations
+		);
+		let [ x, y ] = self.value(
+			at, Field::Position,
+			base.map(|x| [ x.x, x.y ]).as_ref(), &[ ours.x, ours.y ], &[ theirs.x, theirs.y ],
+			|[ x, y ]| format!("({:.3}, {:.3})", x, y)
+		);
+		
+		SchemaBone { label, labels, x, y, ..ours }
+	}
+	
+	// Merges a field, taking whichever side changed it.
+	fn value<T: Clone + PartialEq>(
+		&mut self,
+		at: Location<'_>,
+		field: Field,
+		base: Option<&T>,
+		ours: &T,
+		theirs: &T,
+		describe: impl Fn(&T) -> String
+	) -> T {
+		if ours == theirs || base == Some(theirs) {
+			return ours.clone();
+		}
+		if base == Some(ours) {
+			return theirs.clone();
+		}
+		match self.conflict(at, field, describe(ours), describe(theirs)) {
+			Side::Ours => ours.clone(),
+			Side::Theirs => theirs.clone()
+		}
+	}
+	
+	// Whether an item is kept. Deleting it on one side while changing it on the other conflicts.
+	fn presence<T: PartialEq>(
+		&mut self,
+		at: Location<'_>,
+		field: Field,
+		base: Option<&T>,
+		ours: Option<&T>,
+		theirs: Option<&T>
+	) -> bool {
+		match (base, ours, theirs) {
+			(_, Some(_), Some(_)) => true,
+			(None, _, _) => true,
+			(Some(_), None, None) => false,
+			(Some(base), Some(ours), None) => {
+				ours != base && self.conflict(at, field, "changed".to_string(), "deleted".to_string()) == Side::Ours
+			},
+			(Some(base), None, Some(theirs)) => {
+				theirs != base && self.conflict(at, field, "deleted".to_string(), "changed".to_string()) == Side::Theirs
+			}
+		}
+	}
+	
+	fn conflict(&mut self, at: Location<'_>, field: Field, ours: String, theirs: String) -> Side {
+		let side = self.sides.get(self.conflicts.len()).copied().unwrap_or_default();
+		self.conflicts.push(Conflict {
+			view: at.0.map(str::to_string),
+			bone: at.1.map(str::to_string),
+			field,
+			ours,
+			theirs,
+			side
+		});
+		side
+	}
+}
+
+// An item's name and how many items before it share that name.
+type Key = (String, usize);
+
+// Keys in ours order, followed by keys only theirs has.
+fn keys<T>(ours: &[T], theirs: &[T], name: impl Fn(&T) -> &String) -> Vec<Key> {
+	let mut keys: Vec<Key> = Vec::new();
+	for items in [ ours, theirs ] {
+		for (i, item) in items.iter().enumerate() {
+			let occurrence = items[..i].iter().filter(|x| name(x) == name(item)).count();
+			let key = (name(item).clone(), occurrence);
+			if !keys.contains(&key) {
+				keys.push(key);
+			}
+		}
+	}
+	keys
+}
+
+fn find<'a, T>(items: &'a [T], (key, occurrence): &Key, name: impl Fn(&T) -> &String) -> Option<&'a T> {
+	items.iter().filter(|x| name(x) == key).nth(*occurrence)
+}
+
+// Later items sharing a name are numbered in conflicts, the first keeps the plain name.
+fn describe_key((name, occurrence): &Key) -> String {
+	match occurrence {
+		0 => name.clone(),
+		n => format!("{} ({})", name, n + 1)
+	}
+}
+
+fn describe_translations(translations: &Translations) -> String {
+	if translations.is_empty() {
+		return "(none)".to_string();
+	}
+	let list: Vec<String> = translations.iter()
+		.map(|(lang, text)| format!("{}: \"{}\"", lang, text))
+		.collect();
+	list.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	fn bone(name: &str, x: f32, y: f32) -> SchemaBone {
+		SchemaBone { label: name.to_string(), name: name.to_string(), x, y, ..SchemaBone::default() }
+	}
+	
+	fn schema(bones: Vec<SchemaBone>) -> SchemaData {
+		let view = SchemaView { name: "Front".to_string(), bones, ..SchemaView::default() };
+		SchemaData { views: vec![ view ], ..SchemaData::default() }
+	}
+	
+	fn positions(data: &SchemaData) -> Vec<(String, [f32; 2])> {
+		data.views[0].bones.iter().map(|bone| (bone.name.clone(), [ bone.x, bone.y ])).collect()
+	}
+	
+	fn pos(name: &str, x: f32, y: f32) -> (String, [f32; 2]) {
+		(name.to_string(), [ x, y ])
+	}
+	
+	#[test]
+	fn change_on_one_side_is_taken() {
+		let base = schema(vec![ bone("a", 0.1, 0.1), bone("b", 0.5, 0.5) ]);
+		let ours = schema(vec![ bone("a", 0.2, 0.2), bone("b", 0.5, 0.5) ]);
+		let theirs = schema(vec![ bone("a", 0.1, 0.1), bone("b", 0.6, 0.6) ]);
+		
+		let merge = SchemaMerge::new(base, ours, theirs);
+		assert!(merge.is_clean());
+		assert_eq!(positions(&merge.result()), vec![ pos("a", 0.2, 0.2), pos("b", 0.6, 0.6) ]);
+	}
+	
+	#[test]
+	fn same_change_on_both_sides_is_clean() {
+		let base = schema(vec![ bone("a", 0.1, 0.1) ]);
+		let mut ours = schema(vec![ bone("a", 0.3, 0.3) ]);
+		ours.views[0].bones[0].label = "Head".to_string();
+		let theirs = ours.clone();
+		
+		let merge = SchemaMerge::new(base, ours, theirs);
+		assert!(merge.is_clean());
+		let result = merge.result();
+		assert_eq!(result.views[0].bones[0].label, "Head");
+		assert_eq!(positions(&result), vec![ pos("a", 0.3, 0.3) ]);
+	}
+	
+	#[test]
+	fn field_conflict_takes_the_picked_side() {
+		let base = schema(vec![ bone("a", 0.1, 0.1) ]);
+		let ours = schema(vec![ bone("a", 0.2, 0.2) ]);
+		let theirs = schema(vec![ bone("a", 0.3, 0.3) ]);
+		
+		let mut merge = SchemaMerge::new(base, ours, theirs);
+		assert_eq!(merge.conflicts.len(), 1);
+		assert!(merge.conflicts[0].field == Field::Position);
+		assert_eq!(merge.conflicts[0].bone.as_deref(), Some("a"));
+		assert_eq!(positions(&merge.result()), vec![ pos("a", 0.2, 0.2) ]);
+		
+		merge.pick_all(Side::Theirs);
+		assert_eq!(positions(&merge.result()), vec![ pos("a", 0.3, 0.3) ]);
+	}
+	
+	#[test]
+	fn delete_without_changes_is_clean() {
+		let base = schema(vec![ bone("a", 0.1, 0.1), bone("b", 0.5, 0.5) ]);
+		let ours = schema(vec![ bone("b", 0.5, 0.5) ]);
+		let theirs = base.clone();
+		
+		let merge = SchemaMerge::new(base, ours, theirs);
+		assert!(merge.is_clean());
+		assert_eq!(positions(&merge.result()), vec![ pos("b", 0.5, 0.5) ]);
+	}
+	
+	#[test]
+	fn delete_against_change_conflicts() {
+		let base = schema(vec![ bone("a", 0.1, 0.1) ]);
+		let deleted = schema(vec![]);
+		let changed = schema(vec![ bone("a", 0.4, 0.4) ]);
+		
+		// Ours deleted, theirs changed.
+		let mut merge = SchemaMerge::new(base.clone(), deleted.clone(), changed.clone());
+		assert_eq!(merge.conflicts.len(), 1);
+		assert!(merge.conflicts[0].field == Field::Bone);
+		assert_eq!((merge.conflicts[0].ours.as_str(), merge.conflicts[0].theirs.as_str()), ("deleted", "changed"));
+		assert!(positions(&merge.result()).is_empty());
+		merge.pick_all(Side::Theirs);
+		assert_eq!(positions(&merge.result()), vec![ pos("a", 0.4, 0.4) ]);
+		
+		// Ours changed, theirs deleted.
+		let mut merge = SchemaMerge::new(base, changed, deleted);
+		assert_eq!(merge.conflicts.len(), 1);
+		assert_eq!((merge.conflicts[0].ours.as_str(), merge.conflicts[0].theirs.as_str()), ("changed", "deleted"));
+		assert_eq!(positions(&merge.result()), vec![ pos("a", 0.4, 0.4) ]);
+		merge.pick_all(Side::Theirs);
+		assert!(positions(&merge.result()).is_empty());
+	}
+	
+	#[test]
+	fn bones_sharing_a_name_are_kept_apart() {
+		let base = schema(vec![ bone("a", 0.1, 0.1), bone("a", 0.2, 0.2) ]);
+		let ours = schema(vec![ bone("a", 0.1, 0.1), bone("a", 0.5, 0.5) ]);
+		let theirs = schema(vec![ bone("a", 0.6, 0.6), bone("a", 0.2, 0.2), bone("a", 0.9, 0.9) ]);
+		
+		let merge = SchemaMerge::new(base, ours, theirs);
+		assert!(merge.is_clean());
+		assert_eq!(positions(&merge.result()), vec![ pos("a", 0.6, 0.6), pos("a", 0.5, 0.5), pos("a", 0.9, 0.9) ]);
+	}
+	
+	#[test]
+	fn conflict_on_a_repeated_name_is_numbered() {
+		let base = schema(vec![ bone("a", 0.1, 0.1), bone("a", 0.2, 0.2) ]);
+		let ours = schema(vec![ bone("a", 0.1, 0.1), bone("a", 0.3, 0.3) ]);
+		let theirs = schema(vec![ bone("a", 0.1, 0.1), bone("a", 0.4, 0.4) ]);
+		
+		let merge = SchemaMerge::new(base, ours, theirs);
+		assert_eq!(merge.conflicts.len(), 1);
+		assert_eq!(merge.conflicts[0].bone.as_deref(), Some("a (2)"));
+	}
+	
+	#[test]
+	fn result_replays_sides_in_conflict_order() {
+		let mut base = schema(vec![ bone("a", 0.1, 0.1), bone("b", 0.5, 0.5) ]);
+		base.language = Some("en".to_string());
+		let mut ours = schema(vec![ bone("a", 0.2, 0.2), bone("b", 0.6, 0.6) ]);
+		ours.language = Some("ja".to_string());
+		let mut theirs = schema(vec![ bone("a", 0.3, 0.3), bone("b", 0.7, 0.7) ]);
+		theirs.language = Some("fr".to_string());
+		
+		let mut merge = SchemaMerge::new(base, ours, theirs);
+		let fields: Vec<(Field, Option<&str>)> = merge.conflicts.iter()
+			.map(|x| (x.field, x.bone.as_deref()))
+			.collect();
+		assert!(fields == vec![ (Field::Language, None), (Field::Position, Some("a")), (Field::Position, Some("b")) ]);
+		
+		merge.conflicts[0].side = Side::Theirs;
+		merge.conflicts[2].side = Side::Theirs;
+		let result = merge.result();
+		assert_eq!(result.language.as_deref(), Some("fr"));
+		assert_eq!(positions(&result), vec![ pos("a", 0.2, 0.2), pos("b", 0.7, 0.7) ]);
+		
+		merge.conflicts[0].side = Side::Ours;
+		merge.conflicts[1].side = Side::Theirs;
+		merge.conflicts[2].side = Side::Ours;
+		let result = merge.result();
+		assert_eq!(result.language.as_deref(), Some("ja"));
+		assert_eq!(positions(&result), vec![ pos("a", 0.3, 0.3), pos("b", 0.6, 0.6) ]);
+	}
+}
\ No newline at end of file
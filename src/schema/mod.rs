@@ -1,4 +1,10 @@
-﻿pub mod data;
+﻿pub mod csv;
+pub mod data;
+pub mod diff;
+pub mod merge;
+pub mod pose;
+pub mod project;
+pub mod skeleton;
 mod serializer;
 mod file;
 
@@ -0,0 +1,280 @@
+﻿use crate::schema::{data::{SchemaBone, SchemaView}, skeleton};
+use std::{fs::read_to_string, path::Path};
+use serde_json::{Map, Value};
+
+// Pose projection
+//
+// Reads world-space bone positions from JSON and projects them into normalized image space:
+//
+// {
+//   "bones": { "j_kao": [ x, y, z ], ... },
+//   "camera": { "view": [ ... ], "projection": [ ... ] }
+// }
+//
+// Bones may also be a list of `{ "name", "position" }` or `{ "name", "x", "y", "z" }` objects.
+// Matrices are 16 values in column-major order, which is also the memory layout of
+// System.Numerics matrices. A single `viewProjection` may be given instead, or an orthographic
+// camera as `{ "axis": "-z", "bounds": [ left, right, bottom, top ] }` with optional bounds.
+
+#[derive(Clone)]
+pub struct PoseBone {
+	pub name: String,
+	pub position: [f32; 3]
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Camera {
+	// Combined view-projection matrix.
+	Matrix([f32; 16]),
+	Orthographic { axis: Axis, bounds: Option<[f32; 4]> }
+}
+
+// Direction the orthographic camera looks along. Y is up, except when looking along Y.
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum Axis {
+	PosX,
+	NegX,
+	PosY,
+	NegY,
+	PosZ,
+	#[default]
+	NegZ
+}
+
+pub struct Pose {
+	pub bones: Vec<PoseBone>,
+	pub camera: Option<Camera>
+}
+
+impl Axis {
+	pub const ALL: [Axis; 6] = [ Axis::NegZ, Axis::PosZ, Axis::NegX, Axis::PosX, Axis::NegY, Axis::PosY ];
+	
+	pub fn label(self) -> &'static str {
+		match self {
+			Axis::PosX => "+X",
+			Axis::NegX => "-X",
+			Axis::PosY => "+Y (from below)",
+			Axis::NegY => "-Y (from above)",
+			Axis::PosZ => "+Z",
+			Axis::NegZ => "-Z"
+		}
+	}
+	
+	fn parse(text: &str) -> Option<Self> {
+		match text.to_lowercase().as_str() {
+			"+x" | "x" => Some(Axis::PosX),
+			"-x" => Some(Axis::NegX),
+			"+y" | "y" => Some(Axis::PosY),
+			"-y" => Some(Axis::NegY),
+			"+z" | "z" => Some(Axis::PosZ),
+			"-z" => Some(Axis::NegZ),
+			_ => None
+		}
+	}
+	
+	// Right and up directions on screen.
+	fn basis(self) -> ([f32; 3], [f32; 3]) {
+		match self {
+			Axis::PosX => ([ 0.0, 0.0, 1.0 ], [ 0.0, 1.0, 0.0 ]),
+			Axis::NegX => ([ 0.0, 0.0, -1.0 ], [ 0.0, 1.0, 0.0 ]),
+			Axis::PosY => ([ 1.0, 0.0, 0.0 ], [ 0.0, 0.0, 1.0 ]),
+			Axis::NegY => ([ 1.0, 0.0, 0.0 ], [ 0.0, 0.0, -1.0 ]),
+			Axis::PosZ => ([ -1.0, 0.0, 0.0 ], [ 0.0, 1.0, 0.0 ]),
+			Axis::NegZ => ([ 1.0, 0.0, 0.0 ], [ 0.0, 1.0, 0.0 ])
+		}
+	}
+}
+
+impl Pose {
+	pub fn read(path: &Path) -> Result<Self, String> {
+		let content = read_to_string(path).map_err(|err| err.to_string())?;
+		let value: Value = serde_json::from_str(content.trim_start_matches('\u{feff}'))
+			.map_err(|err| err.to_string())?;
+		
+		let Value::Object(root) = value else {
+			return Err("Expected an object with \"bones\".".to_string())
+		};
+		
+		let bones = read_bones(root.get("bones"))?;
+		let camera = match root.get("camera") {
+			Some(Value::Object(camera)) => Some(read_camera(camera)?),
+			_ => None
+		};
+		
+		Ok(Self { bones, camera })
+	}
+	
+	// Normalized image positions, `None` for bones behind the camera.
+	// `aspect` is the image width over height, used to fit orthographic cameras without bounds.
+	pub fn project(&self, camera: Camera, aspect: f32, margin: f32) -> Vec<Option<[f32; 2]>> {
+		match camera {
+			Camera::Matrix(matrix) => self.bones.iter()
+				.map(|bone| project_matrix(&matrix, bone.position))
+				.collect(),
+			Camera::Orthographic { axis, bounds } => {
+				let (right, up) = axis.basis();
+				let points: Vec<[f32; 2]> = self.bones.iter()
+					.map(|bone| [ dot(bone.position, right), dot(bone.position, up) ])
+					.collect();
+				let bounds = bounds.unwrap_or_else(|| fit_bounds(&points, aspect, margin));
+				let [ left, right, bottom, top ] = bounds;
+				points.into_iter()
+					.map(|[ u, v ]| Some([ (u - left) / (right - left), (top - v) / (top - bottom) ]))
+					.collect()
+			}
+		}
+	}
+}
+
+// Moves bones in `view` matched by name, and creates the rest with a label derived from the name.
+// Positions are clamped to the image. Returns the number of updated and added bones.
+pub fn apply(view: &mut SchemaView, positions: &[(String, [f32; 2])]) -> (usize, usize) {
+	let (mut updated, mut added) = (0, 0);
+	for (name, [ x, y ]) in positions {
+		let (x, y) = (x.clamp(0.0, 1.0), y.clamp(0.0, 1.0));
+		match view.bones.iter_mut().find(|bone| bone.name == *name) {
+			Some(bone) => {
+				bone.x = x;
+				bone.y = y;
+				updated += 1;
+			},
+			None => {
+				let label = view.unique_label(&skeleton::label_from_name(name));
+				view.bones.push(SchemaBone {
+					label,
+					name: name.clone(),
+					x,
+					y,
+					..SchemaBone::default()
+				});
+				added += 1;
+			}
+		}
+	}
+	(updated, added)
+}
+
+pub fn view_projection(view: &[f32; 16], projection: &[f32; 16]) -> [f32; 16] {
+	let mut result = [ 0.0; 16 ];
+	for col in 0..4 {
+		for row in 0..4 {
+			result[col * 4 + row] = (0..4).map(|k| projection[k * 4 + row] * view[col * 4 + k]).sum();
+		}
+	}
+	result
+}
+
+fn project_matrix(matrix: &[f32; 16], [ x, y, z ]: [f32; 3]) -> Option<[f32; 2]> {
+	let clip = |row: usize| matrix[row] * x + matrix[4 + row] * y + matrix[8 + row] * z + matrix[12 + row];
+	let w = clip(3);
+	if w <= f32::EPSILON { return None }
+	
+	let (ndc_x, ndc_y) = (clip(0) / w, clip(1) / w);
+	Some([ (ndc_x + 1.0) * 0.5, (1.0 - ndc_y) * 0.5 ])
+}
+
+// Bounds around every point with `margin` left on each side, keeping the image aspect ratio.
+fn fit_bounds(points: &[[f32; 2]], aspect: f32, margin: f32) -> [f32; 4] {
+	let mut min = [ f32::MAX; 2 ];
+	let mut max = [ f32::MIN; 2 ];
+	for point in points {
+		for i in 0..2 {
+			min[i] = min[i].min(point[i]);
+			max[i] = max[i].max(point[i]);
+		}
+	}
+	if points.is_empty() {
+		(min, max) = ([ -1.0; 2 ], [ 1.0; 2 ]);
+	}
+	
+	let center = [ (min[0] + max[0]) * 0.5, (min[1] + max[1]) * 0.5 ];
+	let aspect = aspect.max(f32::EPSILON);
+	let height = ((max[1] - min[1]).max((max[0] - min[0]) / aspect) / (1.0 - 2.0 * margin.clamp(0.0, 0.45)))
+		.max(f32::EPSILON);
+	let width = height * aspect;
+	
+	[ center[0] - width * 0.5, center[0] + width * 0.5, center[1] - height * 0.5, center[1] + height * 0.5 ]
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+	a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+// Reading
+
+fn read_bones(value: Option<&Value>) -> Result<Vec<PoseBone>, String> {
+	let bones: Vec<PoseBone> = match value {
+		Some(Value::Object(map)) => map.iter()
+			.filter_map(|(name, pos)| Some(PoseBone { name: name.clone(), position: read_vector(pos)? }))
+			.collect(),
+		Some(Value::Array(list)) => list.iter()
+			.filter_map(|item| {
+				let name = item.get("name")?.as_str()?.to_string();
+				let position = match item.get("position") {
+					Some(pos) => read_vector(pos)?,
+					None => read_vector(item)?
+				};
+				Some(PoseBone { name, position })
+			})
+			.collect(),
+		_ => return Err("Expected \"bones\" as an object or a list.".to_string())
+	};
+	
+	match bones.is_empty() {
+		true => Err("No bone positions found in file.".to_string()),
+		false => Ok(bones)
+	}
+}
+
+// Accepts `[ x, y, z ]` or `{ "x", "y", "z" }`.
+fn read_vector(value: &Value) -> Option<[f32; 3]> {
+	let get = |i: usize, key: &str| match value {
+		Value::Array(list) => list.get(i)?.as_f64(),
+		Value::Object(map) => map.get(key)?.as_f64(),
+		_ => None
+	};
+	Some([ get(0, "x")? as f32, get(1, "y")? as f32, get(2, "z")? as f32 ])
+}
+
+fn read_matrix(map: &Map<String, Value>, key: &str) -> Result<Option<[f32; 16]>, String> {
+	let Some(value) = map.get(key) else { return Ok(None) };
+	
+	// Nested rows are flattened in order.
+	let mut values = Vec::new();
+	let mut stack = vec![ value ];
+	while let Some(value) = stack.pop() {
+		match value {
+			Value::Array(list) => stack.extend(list.iter().rev()),
+			Value::Number(n) => values.push(n.as_f64().unwrap_or_default() as f32),
+			_ => return Err(format!("Invalid value in \"{}\".", key))
+		}
+	}
+	
+	values.try_into()
+		.map(Some)
+		.map_err(|values: Vec<f32>| format!("Expected 16 values in \"{}\", found {}.", key, values.len()))
+}
+
+fn read_camera(map: &Map<String, Value>) -> Result<Camera, String> {
+	if let Some(matrix) = read_matrix(map, "viewProjection")? {
+		return Ok(Camera::Matrix(matrix));
+	}
+	
+	match (read_matrix(map, "view")?, read_matrix(map, "projection")?) {
+		(Some(view), Some(projection)) => return Ok(Camera::Matrix(view_projection(&view, &projection))),
+		(Some(_), None) | (None, Some(_)) => return Err("Camera needs both \"view\" and \"projection\".".to_string()),
+		(None, None) => ()
+	}
+	
+	let axis = match map.get("axis").and_then(Value::as_str) {
+		Some(text) => Axis::parse(text).ok_or_else(|| format!("Unknown camera axis \"{}\".", text))?,
+		None => return Err("Camera needs matrices or an \"axis\".".to_string())
+	};
+	let bounds = map.get("bounds")
+		.and_then(Value::as_array)
+		.and_then(|list| list.iter().map(|x| x.as_f64().map(|x| x as f32)).collect::<Option<Vec<f32>>>())
+		.and_then(|list| list.try_into().ok())
+		.filter(|&[ left, right, bottom, top ]: &[f32; 4]| left != right && bottom != top);
+	
+	Ok(Camera::Orthographic { axis, bounds })
+}
\ No newline at end of file
@@ -0,0 +1,153 @@
+﻿use std::{
+	collections::BTreeMap,
+	fs::{self, read_to_string},
+	path::{Path, PathBuf}
+};
+use serde::{Serialize, Deserialize};
+
+const SIDECAR_EXT: &str = "pose2d.json";
+
+// Project sidecar
+//
+// Editor-only data stored next to the schema as `<name>.pose2d.json`,
+// so the exported XML never changes shape.
+
+#[derive(Serialize, Deserialize, Default, Clone, PartialEq)]
+pub struct SchemaProject {
+	#[serde(default)]
+	pub views: BTreeMap<String, ProjectView>
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, PartialEq)]
+pub struct ProjectView {
+	// Overlay layers keyed by the file of the image tab they belong to.
+	#[serde(default)]
+	pub layers: BTreeMap<String, Vec<ImageLayer>>
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct ImageLayer {
+	pub file: String,
+	#[serde(default = "default_opacity")]
+	pub opacity: f32,
+	#[serde(default = "default_visible")]
+	pub visible: bool
+}
+
+impl Default for ImageLayer {
+	fn default() -> Self {
+		Self {
+			file: String::new(),
+			opacity: default_opacity(),
+			visible: default_visible()
+		}
+	}
+}
+
+fn default_opacity() -> f32 {
+	0.5
+}
+
+fn default_visible() -> bool {
+	true
+}
+
+impl SchemaProject {
+	pub fn sidecar_path(schema: &Path) -> PathBuf {
+		schema.with_extension(SIDECAR_EXT)
+	}
+	
+	pub fn read(schema: &Path) -> Self {
+		let path = Self::sidecar_path(schema);
+		if !path.exists() { return Self::default() }
+		
+		let content = match read_to_string(&path) {
+			Ok(content) => content,
+			Err(err) => {
+				println!("Failed to read {}: {}", path.display(), err);
+				return Self::default()
+			}
+		};
+		
+		serde_json::from_str(&content).unwrap_or_else(|err| {
+			println!("Failed to parse {}: {}", path.display(), err);
+			Self::default()
+		})
+	}
+	
+	pub fn write(&self, schema: &Path) -> Result<(), String> {
+		let path = Self::sidecar_path(schema);
+		
+		// Avoid littering a sidecar next to schemas that don't need one.
+		if self.is_empty() {
+			if path.exists() {
+				fs::remove_file(&path).map_err(|err| format!("Failed to remove {}: {}", path.display(), err))?;
+			}
+			return Ok(());
+		}
+		
+		let content = serde_json::to_string_pretty(&self).expect("Failed to serialize project.");
+		fs::write(&path, content).map_err(|err| format!("Failed to write {}: {}", path.display(), err))
+	}
+	
+	pub fn is_empty(&self) -> bool {
+		self.views.values().all(|view| view.layers.values().all(Vec::is_empty))
+	}
+	
+	// Layers
+	
+	pub fn layers(&self, view: &str, image: &str) -> &[ImageLayer] {
+		self.views.get(view)
+			.and_then(|view| view.layers.get(image))
+			.map_or(&[], Vec::as_slice)
+	}
+	
+	// Entries only exist while they hold layers, so setting the list a tab already has,
+	// empty or not, leaves the project as it was.
+	pub fn set_layers(&mut self, view: &str, image: &str, layers: Vec<ImageLayer>) {
+		if self.layers(view, image) == layers.as_slice() { return }
+		
+		if !layers.is_empty() {
+			self.views.entry(view.to_string())
+				.or_default()
+				.layers.insert(image.to_string(), layers);
+			return;
+		}
+		
+		let Some(entry) = self.views.get_mut(view) else { return };
+		entry.layers.remove(image);
+		if entry.layers.is_empty() {
+			self.views.remove(view);
+		}
+	}
+	
+	// Renames
+	//
+	// A rename onto a name that already has an entry would replace its layers, so it's refused
+	// and false is returned. The caller keeps the old name then.
+	
+	pub fn rename_view(&mut self, old: &str, new: &str) -> bool {
+		if old == new { return true }
+		if self.views.contains_key(new) { return false }
+		if let Some(view) = self.views.remove(old) {
+			self.views.insert(new.to_string(), view);
+		}
+		true
+	}
+	
+	pub fn rename_image(&mut self, view: &str, old: &str, new: &str) -> bool {
+		if old == new { return true }
+		let Some(view) = self.views.get_mut(view) else { return true };
+		if view.layers.contains_key(new) { return false }
+		if let Some(layers) = view.layers.remove(old) {
+			view.layers.insert(new.to_string(), layers);
+		}
+		true
+	}
+	
+	pub fn remove_image(&mut self, view: &str, image: &str) {
+		if let Some(view) = self.views.get_mut(view) {
+			view.layers.remove(image);
+		}
+	}
+}
\ No newline at end of file
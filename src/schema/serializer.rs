@@ -1,12 +1,12 @@
-﻿use crate::schema::data::{SchemaBone, SchemaData, SchemaImage, SchemaView};
+﻿use crate::schema::data::{SchemaBone, SchemaData, SchemaImage, SchemaView, Translations};
 use std::{io::Read, path::PathBuf};
 use quick_xml::{events::Event, errors::Error, Reader, Writer};
-use quick_xml::events::BytesText;
+use quick_xml::events::{BytesStart, BytesText};
 use quick_xml::events::Event::Comment;
 
-pub trait SchemaSerializer {
+pub trait SchemaSerializer: Sized {
 	fn serialize(&self) -> Result<Vec<u8>, Error>;
-	fn deserialize(path: PathBuf) -> Self;
+	fn try_deserialize(path: PathBuf) -> Result<Self, String>;
 }
 
 impl SchemaSerializer for SchemaData {
@@ -23,12 +23,19 @@ impl SchemaSerializer for SchemaData {
 			)
 		)))?;
 		
-		writer.create_element("Views")
+		let mut views = writer.create_element("Views");
+		if let Some(lang) = &self.language {
+			views = views.with_attribute(("lang", lang.as_str()));
+		}
+		
+		views
 			.write_inner_content(|inner| {
 				for view in &self.views {
 					// View
+					let names = translated_attributes("name", &view.names);
 					inner.create_element("View")
 						.with_attribute(("name", view.name.as_str()))
+						.with_attributes(names.iter().map(|(key, value)| (key.as_str(), value.as_str())))
 						.write_inner_content(|ele| {
 							for img in &view.images {
 								ele.create_element("Image")
@@ -36,15 +43,17 @@ impl SchemaSerializer for SchemaData {
 									.write_empty()?;
 							}
 							for bone in &view.bones {
+								let labels = translated_attributes("label", &bone.labels);
 								ele.create_element("Bone")
 									.with_attribute(("label", bone.label.as_str()))
+									.with_attributes(labels.iter().map(|(key, value)| (key.as_str(), value.as_str())))
 									.with_attribute(("name", bone.name.as_str()))
 									.with_attribute(("x", bone.x.to_string().as_str()))
 									.with_attribute(("y", bone.y.to_string().as_str()))
 									.write_empty()?;
 							}
 							Ok::<(), Error>(())
-						}).expect(&format!("Failed to write view: {}", view.name));
+						})?;
 				}
 				Ok::<(), Error>(())
 			})?;
@@ -52,67 +61,86 @@ impl SchemaSerializer for SchemaData {
 		Ok(buffer)
 	}
 	
-	fn deserialize(path: PathBuf) -> Self {
-		let mut reader = Reader::from_file(path)
-			.expect("Failed to open reader.");
+	fn try_deserialize(path: PathBuf) -> Result<Self, String> {
+		let mut reader = Reader::from_file(&path)
+			.map_err(|err| format!("Failed to open {}: {}", path.display(), err))?;
 		
 		let mut buf = Vec::new();
 		
 		let mut schema = SchemaData::default();
+		// Other XML would read as an empty schema, so the root must be <Views>.
+		let mut has_root = false;
 		let mut cur_view: Option<SchemaView> = None;
+		let mut cur_bone: Option<SchemaBone> = None;
+		// Language and text of a <Label> element being read.
+		let mut cur_label: Option<(String, String)> = None;
 		
 		loop {
 			match reader.read_event_into(&mut buf) {
-				Err(e) => panic!("Error as position {}: {:?}", reader.error_position(), e),
+				Err(err) => return Err(format!("Invalid XML at {}: {}", reader.error_position(), err)),
 				Ok(Event::Eof) => break,
 				Ok(Event::Start(e)) => {
+					has_root = has_root || is_root(&e)?;
 					match e.name().as_ref() {
-						b"View" => {
-							let mut view = SchemaView::default();
-							if let Ok(Some(attr)) = e.try_get_attribute("name") {
-								attr.value.as_ref().read_to_string(&mut view.name).ok();
-							}
-							cur_view = Some(view);
+						b"Views" => {
+							schema.language = read_attributes(&e).into_iter()
+								.find(|(key, _)| key == "lang")
+								.map(|(_, value)| value);
+						},
+						b"View" => cur_view = Some(read_view(&e)),
+						b"Bone" => cur_bone = Some(read_bone(&e)?),
+						b"Label" => {
+							let lang = read_attributes(&e).into_iter()
+								.find(|(key, _)| key == "lang")
+								.map(|(_, value)| value);
+							cur_label = lang.map(|lang| (lang, String::new()));
 						},
 						_ => ()	
 					};
 				},
 				Ok(Event::Empty(e)) => {
+					has_root = has_root || is_root(&e)?;
 					match e.name().as_ref() {
 						b"Image" => {
 							let mut img = SchemaImage::default();
 							if let Ok(Some(attr)) = e.try_get_attribute("file") {
 								attr.value.as_ref().read_to_string(&mut img.file).ok();
 							}
-							cur_view.as_mut().unwrap().images.push(img);
+							outside_view(cur_view.as_mut(), "Image")?.images.push(img);
 						},
 						b"Bone" => {
-							let mut bone = SchemaBone::default();
-							for attr in e.attributes() {
-								if let Ok(attr) = attr {
-									let mut name = String::new();
-									let mut value = String::new();
-									attr.key.as_ref().read_to_string(&mut name).ok();
-									attr.value.as_ref().read_to_string(&mut value).ok();
-									match name.as_str() {
-										"label" => bone.label = value,
-										"name" => bone.name = value,
-										"x" => bone.x = value.parse().unwrap(),
-										"y" => bone.y = value.parse().unwrap(),
-										_ => ()
-									}
-								}
-							}
-							cur_view.as_mut().unwrap().bones.push(bone);
+							let bone = read_bone(&e)?;
+							outside_view(cur_view.as_mut(), "Bone")?.bones.push(bone);
 						},
 						_ => ()
 					};
 				},
+				Ok(Event::Text(e)) => {
+					if let Some((_, text)) = cur_label.as_mut() {
+						text.push_str(&e.unescape().unwrap_or_default());
+					}
+				},
 				Ok(Event::End(e)) => {
 					match e.name().as_ref() {
+						b"Label" => {
+							// Labels belong to the enclosing bone, or the view outside of one.
+							if let Some((lang, text)) = cur_label.take() {
+								let text = text.trim().to_string();
+								match (cur_bone.as_mut(), cur_view.as_mut()) {
+									(Some(bone), _) => { bone.labels.insert(lang, text); },
+									(None, Some(view)) => { view.names.insert(lang, text); },
+									_ => ()
+								}
+							}
+						},
+						b"Bone" => {
+							let Some(bone) = cur_bone.take() else { continue };
+							outside_view(cur_view.as_mut(), "Bone")?.bones.push(bone);
+						},
 						b"View" => {
-							let view = cur_view.take().unwrap();
-							schema.views.push(view);
+							if let Some(view) = cur_view.take() {
+								schema.views.push(view);
+							}
 						},
 						_ => ()
 					}
@@ -121,6 +149,76 @@ impl SchemaSerializer for SchemaData {
 			};
 		}
 		
-		schema
+		match has_root {
+			true => Ok(schema),
+			false => Err("Not a schema, the file has no <Views> element.".to_string())
+		}
+	}
+}
+
+fn is_root(e: &BytesStart) -> Result<bool, String> {
+	match e.name().as_ref() {
+		b"Views" => Ok(true),
+		name => Err(format!("Not a schema, the root element is <{}>.", String::from_utf8_lossy(name)))
+	}
+}
+
+fn outside_view<'a>(view: Option<&'a mut SchemaView>, element: &str) -> Result<&'a mut SchemaView, String> {
+	view.ok_or_else(|| format!("<{}> outside of a <View>.", element))
+}
+
+fn read_attributes(e: &BytesStart) -> Vec<(String, String)> {
+	e.attributes()
+		.flatten()
+		.map(|attr| {
+			let key = String::from_utf8_lossy(attr.key.as_ref()).into_owned();
+			let value = attr.unescape_value().map(|x| x.into_owned()).unwrap_or_default();
+			(key, value)
+		})
+		.collect()
+}
+
+fn read_view(e: &BytesStart) -> SchemaView {
+	let mut view = SchemaView::default();
+	for (key, value) in read_attributes(e) {
+		match key.split_once('.') {
+			Some(("name", lang)) => { view.names.insert(lang.to_string(), value); },
+			None if key == "name" => view.name = value,
+			_ => ()
+		}
 	}
+	view
+}
+
+fn read_bone(e: &BytesStart) -> Result<SchemaBone, String> {
+	let mut bone = SchemaBone::default();
+	let number = |key: &str, value: &str| value.trim().parse::<f32>()
+		.map_err(|_| format!("Bone \"{}\" has an invalid {} coordinate \"{}\".", bone_name(e), key, value));
+	for (key, value) in read_attributes(e) {
+		match key.split_once('.') {
+			Some(("label", lang)) => { bone.labels.insert(lang.to_string(), value); },
+			None => match key.as_str() {
+				"label" => bone.label = value,
+				"name" => bone.name = value,
+				"x" => bone.x = number("x", &value)?,
+				"y" => bone.y = number("y", &value)?,
+				_ => ()
+			},
+			_ => ()
+		}
+	}
+	Ok(bone)
+}
+
+fn bone_name(e: &BytesStart) -> String {
+	read_attributes(e).into_iter()
+		.find(|(key, _)| key == "name")
+		.map_or(String::new(), |(_, value)| value)
+}
+
+// Translations are written as `label.ja="..."` style attributes.
+fn translated_attributes(key: &str, translations: &Translations) -> Vec<(String, String)> {
+	translations.iter()
+		.map(|(lang, text)| (format!("{}.{}", key, lang), text.clone()))
+		.collect()
 }
\ No newline at end of file
@@ -0,0 +1,235 @@
+﻿use crate::schema::data::{SchemaBone, SchemaView};
+use std::{collections::HashSet, fs::read_to_string, path::Path};
+use quick_xml::{events::{BytesStart, Event}, Reader};
+use serde_json::Value;
+
+// Skeleton bone lists
+//
+// Bone names read from plain text (one per line), JSON, or a Havok skeleton
+// dump in either the packfile (`hkparam`) or tagfile (`field`) XML layout.
+
+#[derive(Clone)]
+pub struct SkeletonBone {
+	pub name: String,
+	pub parent: Option<usize>
+}
+
+pub fn read_bone_list(path: &Path) -> Result<Vec<SkeletonBone>, String> {
+	let content = read_to_string(path).map_err(|err| err.to_string())?;
+	let content = content.trim_start_matches('\u{feff}').trim_start();
+	
+	let bones = match content.chars().next() {
+		Some('[' | '{') => parse_json(content)?,
+		Some('<') => parse_havok(content)?,
+		_ => parse_text(content)
+	};
+	
+	match bones.is_empty() {
+		true => Err("No bones found in file.".to_string()),
+		false => Ok(bones)
+	}
+}
+
+// Depth of each bone in the hierarchy, for indenting lists.
+pub fn depths(bones: &[SkeletonBone]) -> Vec<usize> {
+	let mut depths = vec![ 0; bones.len() ];
+	for i in 0..bones.len() {
+		let mut parent = bones[i].parent;
+		// Capped in case of cycles in malformed files.
+		while let Some(p) = parent.filter(|_| depths[i] < bones.len()) {
+			depths[i] += 1;
+			parent = bones.get(p).and_then(|bone| bone.parent);
+		}
+	}
+	depths
+}
+
+// Readable label from a game bone name, e.g. `j_ude_a_l` becomes `Ude A (L)`.
+pub fn label_from_name(name: &str) -> String {
+	let trimmed = ["j_", "n_", "iv_", "ex_"].iter()
+		.find_map(|prefix| name.strip_prefix(prefix))
+		.filter(|rest| !rest.is_empty())
+		.unwrap_or(name);
+	
+	let mut words: Vec<&str> = trimmed.split(['_', ' ', '-']).filter(|x| !x.is_empty()).collect();
+	let side = match words.last() {
+		Some(&"l") if words.len() > 1 => Some("(L)"),
+		Some(&"r") if words.len() > 1 => Some("(R)"),
+		_ => None
+	};
+	if side.is_some() {
+		words.pop();
+	}
+	
+	let mut label: Vec<String> = words.iter().map(|word| {
+		let mut chars = word.chars();
+		match chars.next() {
+			Some(first) => first.to_uppercase().chain(chars).collect(),
+			None => String::new()
+		}
+	}).collect();
+	label.extend(side.map(str::to_string));
+	
+	match label.is_empty() {
+		true => name.to_string(),
+		false => label.join(" ")
+	}
+}
+
+// Creates bones for `names` in `view`, laid out on a grid so each can be picked out and placed.
+// Names already in the view are skipped. Returns the labels of the new bones.
+pub fn add_bones(view: &mut SchemaView, names: &[String]) -> Vec<String> {
+	let mut names: Vec<&String> = names.iter()
+		.filter(|name| !view.bones.iter().any(|bone| bone.name == **name))
+		.collect();
+	let mut seen = HashSet::new();
+	names.retain(|name| seen.insert(*name));
+	
+	let cols = (names.len() as f32).sqrt().ceil().max(1.0) as usize;
+	let rows = names.len().div_ceil(cols);
+	
+	let mut added = Vec::new();
+	for (i, name) in names.into_iter().enumerate() {
+		let label = view.unique_label(&label_from_name(name));
+		view.bones.push(SchemaBone {
+			label: label.clone(),
+			name: name.clone(),
+			x: ((i % cols) as f32 + 0.5) / cols as f32,
+			y: ((i / cols) as f32 + 0.5) / rows as f32,
+			..SchemaBone::default()
+		});
+		added.push(label);
+	}
+	added
+}
+
+// Plain text
+
+fn parse_text(content: &str) -> Vec<SkeletonBone> {
+	content.lines()
+		.map(|line| line.split(['#', ';']).next().unwrap_or_default())
+		.filter_map(|line| {
+			// Dumps often prefix lines with an index, like `3: j_kao` or `[3] j_kao`.
+			line.split(|c: char| c.is_whitespace() || c == ',')
+				.map(|token| token.trim_matches(|c: char| "[]():\"'".contains(c)))
+				.find(|token| !token.is_empty() && !token.chars().all(|c| c.is_ascii_digit() || c == '-'))
+		})
+		.map(|name| SkeletonBone { name: name.to_string(), parent: None })
+		.collect()
+}
+
+// JSON
+
+// Accepts a list of names, or of objects with a `name` and an optional `parent`
+// given as an index or a name, either at the top level or under `bones`.
+fn parse_json(content: &str) -> Result<Vec<SkeletonBone>, String> {
+	let value: Value = serde_json::from_str(content).map_err(|err| err.to_string())?;
+	let list = match &value {
+		Value::Array(list) => list,
+		Value::Object(map) => match map.get("bones") {
+			Some(Value::Array(list)) => list,
+			_ => return Err("Expected a \"bones\" array.".to_string())
+		},
+		_ => return Err("Expected a list of bones.".to_string())
+	};
+	
+	let mut bones = Vec::new();
+	let mut parents = Vec::new();
+	for item in list {
+		let (name, parent) = match item {
+			Value::String(name) => (name.clone(), None),
+			Value::Object(map) => match map.get("name").and_then(Value::as_str) {
+				Some(name) => (name.to_string(), map.get("parent").cloned()),
+				None => continue
+			},
+			_ => continue
+		};
+		bones.push(SkeletonBone { name, parent: None });
+		parents.push(parent);
+	}
+	
+	for (i, parent) in parents.into_iter().enumerate() {
+		bones[i].parent = match parent {
+			Some(Value::Number(n)) => n.as_u64().map(|n| n as usize).filter(|n| *n < bones.len() && *n != i),
+			Some(Value::String(name)) => bones.iter().position(|bone| bone.name == name).filter(|n| *n != i),
+			_ => None
+		};
+	}
+	
+	Ok(bones)
+}
+
+// Havok XML
+
+fn parse_havok(content: &str) -> Result<Vec<SkeletonBone>, String> {
+	let mut reader = Reader::from_str(content);
+	reader.config_mut().trim_text(true);
+	
+	// Names of the `hkparam`/`field` elements enclosing the current position.
+	let mut params: Vec<String> = Vec::new();
+	let mut names = Vec::new();
+	let mut parents = String::new();
+	
+	loop {
+		match reader.read_event() {
+			Err(err) => return Err(format!("Invalid XML at {}: {}", reader.error_position(), err)),
+			Ok(Event::Eof) => break,
+			Ok(Event::Start(e)) => match e.name().as_ref() {
+				b"hkparam" | b"field" => params.push(param_name(&e)),
+				_ => ()
+			},
+			Ok(Event::End(e)) => match e.name().as_ref() {
+				b"hkparam" | b"field" => { params.pop(); },
+				_ => ()
+			},
+			Ok(Event::Empty(e)) => {
+				// Tagfile: <field name="name"><string value="j_kao"/></field>
+				let value = e.try_get_attribute("value").ok().flatten()
+					.map(|attr| attr.unescape_value().unwrap_or_default().into_owned());
+				match (e.name().as_ref(), value) {
+					(b"string", Some(value)) if in_bone_name(&params) => names.push(value),
+					(b"int", Some(value)) if params.last().is_some_and(|x| x == "parentIndices") => {
+						parents.push_str(&value);
+						parents.push(' ');
+					},
+					_ => ()
+				}
+			},
+			Ok(Event::Text(e)) => {
+				let text = e.unescape().unwrap_or_default();
+				if in_bone_name(&params) {
+					// Packfile: <hkparam name="name">j_kao</hkparam>
+					names.push(text.into_owned());
+				} else if params.last().is_some_and(|x| x == "parentIndices") {
+					parents.push_str(&text);
+					parents.push(' ');
+				}
+			},
+			_ => ()
+		}
+	}
+	
+	let parents: Vec<Option<usize>> = parents.split_whitespace()
+		.map(|x| x.parse::<i64>().ok().filter(|x| *x >= 0).map(|x| x as usize))
+		.collect();
+	
+	Ok(names.into_iter().enumerate()
+		.map(|(i, name)| SkeletonBone {
+			name,
+			parent: parents.get(i).copied().flatten().filter(|p| *p != i)
+		})
+		.collect())
+}
+
+fn param_name(e: &BytesStart) -> String {
+	e.try_get_attribute("name").ok().flatten()
+		.map(|attr| String::from_utf8_lossy(&attr.value).into_owned())
+		.unwrap_or_default()
+}
+
+fn in_bone_name(params: &[String]) -> bool {
+	match params {
+		[ .., bones, name ] => bones == "bones" && name == "name",
+		_ => false
+	}
+}
\ No newline at end of file